@@ -0,0 +1,172 @@
+// 2人交互ゲームのAI同士の対戦と実行速度の計測
+// chapter5やchapter8など、AlternateGameStateを実装したゲームで共通に使う
+// 対戦はMatchPlayerを実装したAIなら、chapter6の同時手番ゲームでも同じように集計する
+
+use std::rc::Rc;
+
+use crate::random::game_rng;
+use crate::search::take_reached_depths;
+use crate::statistics::{MatchResult, Sprt, SprtStatus, Summary};
+use crate::transposition_table::take_statistics;
+use crate::worker_pool::run_tasks_until;
+use crate::{AIFunction, AlternateGameState, RandomGenerator, StringAIPair};
+
+// 置換表を使うAIの場合はヒット数とミス数も、反復深化を使うAIの場合は探索しきった深さも表示する
+pub fn calculate_execution_speed<S: AlternateGameState>(ai: &StringAIPair<S>, states: &[S], rng: &mut RandomGenerator) {
+    take_statistics();
+    take_reached_depths();
+    let start_time = std::time::Instant::now();
+    for state in states {
+        (ai.1)(state, rng);
+    }
+    let diff = std::time::Instant::now() - start_time;
+    let time = diff.as_millis();
+    println!("{} take {} ms to process {} nodes", ai.0, time, states.len());
+    let (hit, miss) = take_statistics();
+    if hit + miss > 0 {
+        println!(
+            "{} transposition table hit {} miss {} (hit rate {:.3})",
+            ai.0,
+            hit,
+            miss,
+            hit as f64 / (hit + miss) as f64
+        );
+    }
+    let (total_depth, search_number, max_depth) = take_reached_depths();
+    if search_number > 0 {
+        println!(
+            "{} iterative deepening reached depth {:.2} on average (max {})",
+            ai.0,
+            total_depth as f64 / search_number as f64,
+            max_depth
+        );
+    }
+}
+
+// 対戦させるAIの種類ごとの、1ゲームのプレイの仕方
+pub trait MatchPlayer<S> {
+    // firstを先手、secondを後手として終局までプレイし、先手から見た勝率と両者が選んだ行動の列を返す
+    fn play_game(first: &Self, second: &Self, state: S, rng: &mut RandomGenerator) -> (f64, Vec<usize>);
+}
+
+impl<S: AlternateGameState> MatchPlayer<S> for Rc<AIFunction<S>> {
+    fn play_game(first: &Self, second: &Self, state: S, rng: &mut RandomGenerator) -> (f64, Vec<usize>) {
+        play_alternate_game(&**first, &**second, state, rng)
+    }
+}
+
+// first_aiを先手、second_aiを後手として終局までプレイし、先手から見た勝率と両者が選んだ行動の列を返す
+pub fn play_alternate_game<S: AlternateGameState>(
+    first_ai: &AIFunction<S>,
+    second_ai: &AIFunction<S>,
+    mut state: S,
+    rng: &mut RandomGenerator,
+) -> (f64, Vec<usize>) {
+    let mut actions = Vec::new();
+    loop {
+        for ai in [first_ai, second_ai] {
+            let action = ai(&state, rng);
+            actions.push(action);
+            state.advance(action);
+            if state.is_done() {
+                return (state.get_first_player_score_fow_win_rate(), actions);
+            }
+        }
+    }
+}
+
+// 対戦で終えた1ゲーム。firstは先手のAIのaisでの添字で、playersは先手、後手の順のAIの名前
+pub struct PlayedGame<'a, S> {
+    pub index: usize,
+    pub first: usize,
+    pub players: [&'a str; 2],
+    pub initial_state: &'a S,
+    pub actions: &'a [usize],
+}
+
+// new_state(i)から始まるゲームをgame_number×2(先手後手を交代)回プレイしてaisの0番目のAIの成績を表示する。
+// AIの乱数はseedからiごとに作るので(Noneならエントロピーから)、
+// thread_number本のスレッドで並列にプレイしても、seedが同じなら同じ結果をiの順に表示して集計する
+// sprtを指定した場合は、先手後手の2ゲームごとに検定して結論が出たところで打ち切る
+// on_gameには終えたゲームを、スレッド数によらず同じ順(iの順、同じiなら0番目のAIが先手のゲームから)で渡す
+// そのためnew_stateは同じiに対して毎回同じ状態を作るものにする
+pub fn test_first_player_win_rate<A: MatchPlayer<S>, S: Clone>(
+    new_ais: &(dyn Fn() -> [(String, A); 2] + Sync),
+    game_number: usize,
+    new_state: impl Fn(usize) -> S + Sync,
+    seed: Option<u64>,
+    thread_number: usize,
+    sprt: Option<Sprt>,
+    mut on_game: impl FnMut(&PlayedGame<S>),
+) -> MatchResult {
+    let names: Vec<String> = new_ais().into_iter().map(|ai| ai.0).collect();
+    let mut first_player_win_rate = 0.0;
+    let mut result = MatchResult::default();
+    let mut sprt_status = SprtStatus::CONTINUE;
+    let games = run_tasks_until(
+        game_number,
+        thread_number,
+        new_ais,
+        |ais, i| {
+            let base_state = new_state(i);
+            let mut rng = game_rng(seed, i);
+            let mut win_rate_points = [0.0; 2];
+            let mut actions = [Vec::new(), Vec::new()];
+            for (j, win_rate_point) in win_rate_points.iter_mut().enumerate() {
+                let first_ai = &ais[j];
+                let second_ai = &ais[(j + 1) % 2];
+                (*win_rate_point, actions[j]) = A::play_game(&first_ai.1, &second_ai.1, base_state.clone(), &mut rng);
+                if j == 1 {
+                    *win_rate_point = 1.0 - *win_rate_point;
+                }
+            }
+            (win_rate_points, actions)
+        },
+        |i, (win_rate_points, actions)| {
+            // 状態はスレッド間で受け渡せるとは限らないので作り直す
+            let base_state = new_state(i);
+            for (j, actions) in actions.iter().enumerate() {
+                on_game(&PlayedGame {
+                    index: i,
+                    first: j,
+                    players: [&names[j], &names[(j + 1) % 2]],
+                    initial_state: &base_state,
+                    actions,
+                });
+            }
+            first_player_win_rate += win_rate_points[0] + win_rate_points[1];
+            eprintln!("i {} w {}", i, first_player_win_rate / ((i + 1) * 2) as f64);
+            for &win_rate_point in win_rate_points {
+                result.add(win_rate_point);
+            }
+            if let Some(sprt) = sprt {
+                sprt_status = sprt.status(&result);
+            }
+            sprt_status == SprtStatus::CONTINUE
+        },
+    );
+    let win_rate_points: Vec<f64> = games.iter().flat_map(|(win_rate_points, _)| *win_rate_points).collect();
+    let summary = Summary::new(&win_rate_points);
+    println!("Winning rate of {} to {}: {}", names[0], names[1], summary);
+    println!("{} to {}: {}", names[0], names[1], result);
+    if let Some(sprt) = sprt {
+        let (lower, upper) = sprt.bounds();
+        let conclusion = match sprt_status {
+            SprtStatus::H0 => format!("H0 accepted (Elo {} for {})", sprt.elo0, names[0]),
+            SprtStatus::H1 => format!("H1 accepted (Elo {} for {})", sprt.elo1, names[0]),
+            SprtStatus::CONTINUE => "no conclusion".to_string(),
+        };
+        println!(
+            "SPRT(elo0 {}, elo1 {}, alpha {}, beta {}): LLR {:.3} [{:.3}, {:.3}], {}",
+            sprt.elo0,
+            sprt.elo1,
+            sprt.alpha,
+            sprt.beta,
+            sprt.llr(&result),
+            lower,
+            upper,
+            conclusion
+        );
+    }
+    result
+}
@@ -0,0 +1,93 @@
+// chapter3の迷路を標準入力から読み、全体の制限時間の中で探索した行動の列を標準出力に書く
+// 入力の形式はsrc/maze_text.rsを参照(キャラクターは1人)
+// 出力は1行に1ターンずつ、行動をR(右), D(下), L(左), U(上)で表す
+
+use std::io::Read;
+
+use thunder_book::ch3::{solve, MazeState, TimedAIFunction, ACTION_NAMES};
+use thunder_book::cli::{exit_with_usage, Args};
+use thunder_book::maze_text::MazeText;
+use thunder_book::search::{
+    beam_search_action, beam_search_action_with_time_threshold, chokudai_search_action,
+    chokudai_search_action_with_time_threshold, greedy_action,
+};
+use thunder_book::GameState;
+
+const AI_NAMES: [&str; 5] = ["greedy", "beam", "beam-time", "chokudai", "chokudai-time"];
+const KNOWN_OPTIONS: [&str; 4] = ["width", "depth", "number", "time-limit"];
+
+fn usage() -> String {
+    format!(
+        "usage: ch3_solver [AI] [options] < input
+AI: {} (default: chokudai-time)
+options:
+  --width N         beam width
+  --depth N         beam depth
+  --number N        beam number of chokudai search
+  --time-limit SEC  time limit of the whole game; beam-time and chokudai-time share the remaining time
+                    equally among the remaining turns, and greedy is used once it runs out (default: 1.8)",
+        AI_NAMES.join(", ")
+    )
+}
+
+// --width, --depth, --number を読んで、1手の制限時間を受け取るAIを作る
+fn timed_ai(name: &str, args: &Args) -> Result<Box<TimedAIFunction>, String> {
+    let ai: Box<TimedAIFunction> = match name {
+        "greedy" => Box::new(|state, _| greedy_action(state)),
+        "beam" => {
+            let beam_width = args.get("width", 10)?;
+            let beam_depth = args.get("depth", 10)?;
+            Box::new(move |state, _| beam_search_action(state, beam_width, beam_depth))
+        }
+        "beam-time" => {
+            let beam_width = args.get("width", 5)?;
+            Box::new(move |state, time_threshold| {
+                beam_search_action_with_time_threshold(state, beam_width, time_threshold)
+            })
+        }
+        "chokudai" => {
+            let beam_width = args.get("width", 1)?;
+            let beam_depth = args.get("depth", 10)?;
+            let beam_number = args.get("number", 10)?;
+            Box::new(move |state, _| chokudai_search_action(state, beam_width, beam_depth, beam_number))
+        }
+        "chokudai-time" => {
+            let beam_width = args.get("width", 1)?;
+            let beam_depth = args.get("depth", 10)?;
+            Box::new(move |state, time_threshold| {
+                chokudai_search_action_with_time_threshold(state, beam_width, beam_depth, time_threshold)
+            })
+        }
+        _ => return Err(format!("unknown AI: {}", name)),
+    };
+    Ok(ai)
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let ai = timed_ai(args.ai_name(0, "chokudai-time"), args)?;
+    let time_limit = args.get("time-limit", 1.8)?;
+    let mut input = String::new();
+    std::io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|e| format!("cannot read stdin: {}", e))?;
+    let state = MazeState::from_maze_text(&MazeText::parse(&input).map_err(|e| format!("stdin: {}", e))?)?;
+
+    let actions = solve(&state, &*ai, time_limit);
+    let mut final_state = state;
+    let mut output = String::new();
+    for &action in actions.iter() {
+        final_state.advance(action);
+        output.push(ACTION_NAMES[action]);
+        output.push('\n');
+    }
+    print!("{}", output);
+    eprintln!("score:{}", final_state.game_score);
+    Ok(())
+}
+
+fn main() {
+    let args = Args::from_env_or_exit(&KNOWN_OPTIONS, &usage());
+    if let Err(e) = run(&args) {
+        exit_with_usage(&usage(), Some(&e));
+    }
+}
@@ -0,0 +1,135 @@
+// chapter4の小さな盤面で最適な配置を分枝限定法で求め、AIのスコアとの差(最適性ギャップ)をseedごとに表示する
+
+use thunder_book::ch4::{exact_solve, play_state, report_operator_stats, take_operator_stats, MazeState, OperatorStats};
+use thunder_book::cli::{ai_factory, ch4_ai, exit_with_usage, Args, CH4_AI_NAMES, CH4_AI_OPTIONS, CH4_AI_OPTIONS_USAGE};
+use thunder_book::maze_text::{check_moves, MazeText};
+use thunder_book::random::game_seed;
+use thunder_book::statistics::Summary;
+use thunder_book::worker_pool::run_tasks;
+use thunder_book::{new_rng, ScoreType};
+
+const KNOWN_OPTIONS: [&str; 8] = ["height", "width", "turns", "characters", "games", "seed", "jobs", "maze"];
+
+fn usage() -> String {
+    format!(
+        "usage: ch4_exact [AI] [options]
+AI: {} (default: annealing)
+options:
+{}
+  --height N        height of the grid (default: 5)
+  --width N         width of the grid (default: 5)
+  --turns N         number of turns (default: 10)
+  --characters N    number of characters (default: 3)
+  --games N         number of games (default: 10)
+  --seed N|none     seed of the first game; game i and its AI use seed N+i (default: 314)
+  --jobs N          number of threads solving games in parallel (default: 1)
+  --maze FILE       solve only the maze read from FILE (see src/maze_text.rs for the format)",
+        CH4_AI_NAMES.join(", "),
+        CH4_AI_OPTIONS_USAGE
+    )
+}
+
+// 1ゲームの結果
+struct GameResult {
+    optimum: ScoreType,
+    score: ScoreType,
+    evaluated: usize,
+    time: f64,
+    // AIが局所探索なら近傍の操作ごとの採用数
+    stats: OperatorStats,
+}
+impl GameResult {
+    fn gap(&self) -> ScoreType {
+        self.optimum - self.score
+    }
+
+    // 最適なスコアに対するギャップの割合(%)
+    fn gap_rate(&self) -> f64 {
+        if self.optimum == 0 {
+            0.0
+        } else {
+            self.gap() as f64 * 100.0 / self.optimum as f64
+        }
+    }
+}
+
+// cells個のマスにn人のキャラクターを重なりを許して置く組み合わせの数
+fn placement_number(cells: usize, n: usize) -> f64 {
+    (0..n).map(|i| (cells + i) as f64 / (i + 1) as f64).product()
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let new_ai = ai_factory(args, |args| ch4_ai(args.ai_name(0, "annealing"), args))?;
+    let seed = args.get_seed(Some(314))?;
+    let thread_number = args.get("jobs", 1)?;
+    let (states, seeds): (Vec<MazeState>, Vec<Option<u64>>) = match args.get_path("maze") {
+        Some(path) => (vec![MazeState::from_maze_text(&MazeText::load(path)?)?], vec![seed]),
+        None => {
+            let (h, w) = (args.get("height", 5)?, args.get("width", 5)?);
+            let end_turn = args.get("turns", 10)?;
+            let character_number = args.get("characters", 3)?;
+            if h == 0 || w == 0 || character_number == 0 {
+                return Err(format!("invalid size: {}x{} with {} characters", h, w, character_number));
+            }
+            check_moves(h, w, end_turn)?;
+            let game_number = args.get("games", 10)?;
+            (0..game_number)
+                .map(|cnt| {
+                    let seed = game_seed(seed, cnt);
+                    (MazeState::with_size(seed, h, w, end_turn, character_number), seed)
+                })
+                .unzip()
+        }
+    };
+
+    let results = run_tasks(
+        states.len(),
+        thread_number,
+        &new_ai,
+        |ai, cnt| {
+            let state = &states[cnt];
+            let start_time = std::time::Instant::now();
+            let (_, optimum, evaluated) = exact_solve(state);
+            let time = start_time.elapsed().as_secs_f64();
+            take_operator_stats();
+            let score = play_state(&**ai, state, &mut new_rng(seeds[cnt]));
+            GameResult {
+                optimum,
+                score,
+                evaluated,
+                time,
+                stats: take_operator_stats(),
+            }
+        },
+        |cnt, result| {
+            let state = &states[cnt];
+            report_operator_stats(&result.stats);
+            println!(
+                "game: {} optimum:{} ai:{} gap:{} ({:.1}%) evaluated:{}/{} ({:.3}s)",
+                cnt,
+                result.optimum,
+                result.score,
+                result.gap(),
+                result.gap_rate(),
+                result.evaluated,
+                placement_number(state.h * state.w, state.characters.len()),
+                result.time
+            );
+        },
+    );
+    let gaps: Vec<f64> = results.iter().map(|result| result.gap() as f64).collect();
+    let gap_rates: Vec<f64> = results.iter().map(GameResult::gap_rate).collect();
+    let optimal_number = results.iter().filter(|result| result.gap() == 0).count();
+    println!("average gap: {}", Summary::new(&gaps));
+    println!("average gap (%): {}", Summary::new(&gap_rates));
+    println!("optimal games: {}/{}", optimal_number, results.len());
+    Ok(())
+}
+
+fn main() {
+    let known_options = [CH4_AI_OPTIONS.as_slice(), KNOWN_OPTIONS.as_slice()].concat();
+    let args = Args::from_env_or_exit(&known_options, &usage());
+    if let Err(e) = run(&args) {
+        exit_with_usage(&usage(), Some(&e));
+    }
+}
@@ -0,0 +1,52 @@
+// chapter5の棋譜を読み込み、全ての行動が合法かを確かめながら1手ずつ再生する
+
+use thunder_book::cli::{exit_with_usage, Args};
+use thunder_book::game_record::{result_name, GameRecord};
+
+const KNOWN_OPTIONS: [&str; 1] = ["quiet"];
+
+fn usage() -> String {
+    "usage: ch5_replay FILE [options]
+options:
+  --quiet BOOL      only check the record without printing the board (default: false)"
+        .to_string()
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let [path] = &args.positional[..] else {
+        return Err("one game record file is needed".to_string());
+    };
+    let quiet = args.get("quiet", false)?;
+    let record = GameRecord::load(path)?;
+
+    eprintln!("1p: {}, 2p: {}", record.players[0], record.players[1]);
+    if !quiet {
+        eprintln!("initial state");
+        record.initial_state.to_string();
+    }
+    let state = record
+        .replay(|index, state| {
+            if !quiet {
+                eprintln!("{}p action {}-----------------------------------", index % 2 + 1, record.actions[index]);
+                state.to_string();
+            }
+        })
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let scores = state.first_player_scores();
+    println!(
+        "{}: {} actions are legal, scores {} {}, {}",
+        path,
+        record.actions.len(),
+        scores[0],
+        scores[1],
+        result_name(&scores)
+    );
+    Ok(())
+}
+
+fn main() {
+    let args = Args::from_env_or_exit(&KNOWN_OPTIONS, &usage());
+    if let Err(e) = run(&args) {
+        exit_with_usage(&usage(), Some(&e));
+    }
+}
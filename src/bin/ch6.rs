@@ -0,0 +1,51 @@
+// [世界四連覇AIエンジニアがゼロから教えるゲーム木探索入門]
+// chapter6を実装
+// thunder(@thun_c)さんのコードを参考にしました
+
+use thunder_book::ch6::{playGame, test_first_player_win_rate, MazeState};
+use thunder_book::cli::{ai_factory, ch6_ai, exit_with_usage, Args, CH6_AI_NAMES, SPRT_OPTIONS, SPRT_OPTIONS_USAGE};
+use thunder_book::new_rng;
+
+const KNOWN_OPTIONS: [&str; 7] = ["playout", "c", "expand-threshold", "time", "games", "jobs", "seed"];
+
+fn usage() -> String {
+    format!(
+        "usage: ch6 [AI1] [AI2] [options]
+AI: {} (default: duct primitive-mc)
+options:
+  --playout N       number of playouts of primitive-mc and duct (default: 1000)
+  --c C             exploration constant of duct (default: 1.0)
+  --expand-threshold N
+                    number of visits before a node of duct is expanded (default: 5)
+  --time SEC        time limit per action of duct-time (default: 0.01)
+  --games N         number of games for the winning rate (default: 100)
+  --jobs N          number of threads playing games in parallel (default: 1)
+{}
+  --seed N|none     seed of the example game and of the random numbers of the AIs;
+                    none seeds them from entropy (default: 314)",
+        CH6_AI_NAMES.join(", "),
+        SPRT_OPTIONS_USAGE
+    )
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let new_ais = ai_factory(args, |args| {
+        Ok([ch6_ai(args.ai_name(0, "duct"), args)?, ch6_ai(args.ai_name(1, "primitive-mc"), args)?])
+    })?;
+    let game_number = args.get("games", 100)?;
+    let thread_number = args.get("jobs", 1)?;
+    let sprt = args.get_sprt()?;
+    let seed = args.get_seed(Some(314))?;
+
+    test_first_player_win_rate(&new_ais, game_number, seed, thread_number, sprt);
+    playGame(&new_ais(), MazeState::new(seed), &mut new_rng(seed));
+    Ok(())
+}
+
+fn main() {
+    let known_options = [SPRT_OPTIONS.as_slice(), KNOWN_OPTIONS.as_slice()].concat();
+    let args = Args::from_env_or_exit(&known_options, &usage());
+    if let Err(e) = run(&args) {
+        exit_with_usage(&usage(), Some(&e));
+    }
+}
@@ -0,0 +1,53 @@
+// [世界四連覇AIエンジニアがゼロから教えるゲーム木探索入門]
+// chapter7の壁のある迷路を実装
+// thunder(@thun_c)さんのコードを参考にしました
+
+use thunder_book::ch7::{test_AI_score, Evaluation, MazeState, Representation};
+use thunder_book::cli::{ai_factory, exit_with_usage, single_player_ai, Args, SINGLE_PLAYER_AI_NAMES};
+
+const KNOWN_OPTIONS: [&str; 8] = ["width", "depth", "number", "time", "dedup", "games", "seed", "jobs"];
+
+fn usage() -> String {
+    format!(
+        "usage: ch7 [AI] [options]
+plays the same games with the naive evaluation and the distance-aware evaluation,
+computing the distance both on the grid and on bitboards
+AI: {} (default: beam)
+options:
+  --width N         beam width
+  --depth N         beam depth
+  --number N        beam number of chokudai search
+  --time SEC        time limit per action
+  --dedup BOOL      keep only the best state among states with the same hash (default: false)
+  --games N         number of games (default: 100)
+  --seed N|none     seed of the first game; game i and its AI use seed N+i (default: 0)
+  --jobs N          number of threads playing games in parallel (default: 1)",
+        SINGLE_PLAYER_AI_NAMES.join(", ")
+    )
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let new_ai = ai_factory(args, |args| single_player_ai::<MazeState>(args.ai_name(0, "beam"), args))?;
+    let game_number = args.get("games", 100)?;
+    let seed = args.get_seed(Some(0))?;
+    let thread_number = args.get("jobs", 1)?;
+    let settings = [
+        ("naive", Evaluation::NAIVE, Representation::GRID),
+        ("distance(grid)", Evaluation::DISTANCE, Representation::GRID),
+        ("distance(bitboard)", Evaluation::DISTANCE, Representation::BITBOARD),
+    ];
+    for (name, evaluation, representation) in settings {
+        let start_time = std::time::Instant::now();
+        let score = test_AI_score(&new_ai, game_number, seed, evaluation, representation, thread_number);
+        let time = start_time.elapsed().as_millis();
+        println!("{} evaluation average score: {}, take {} ms", name, score, time);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = Args::from_env_or_exit(&KNOWN_OPTIONS, &usage());
+    if let Err(e) = run(&args) {
+        exit_with_usage(&usage(), Some(&e));
+    }
+}
@@ -0,0 +1,81 @@
+// [世界四連覇AIエンジニアがゼロから教えるゲーム木探索入門]
+// chapter8を実装
+// thunder(@thun_c)さんのコードを参考にしました
+
+use thunder_book::battle::calculate_execution_speed;
+use thunder_book::ch8::{
+    get_sample_states, playGame, test_first_player_win_rate, ArrayState, BitboardState, ConnectFourState, OPENING_MOVES,
+};
+use thunder_book::cli::{
+    ai_factory, alternate_ai, alternate_ai_options_usage, exit_with_usage, Args, ALTERNATE_AI_NAMES,
+    ALTERNATE_AI_OPTIONS, SPRT_OPTIONS, SPRT_OPTIONS_USAGE,
+};
+use thunder_book::new_rng;
+
+const DEFAULT_DEPTH: usize = 4;
+
+fn usage() -> String {
+    format!(
+        "usage: ch8 [AI1] [AI2] [options]
+AI: {} (default: thunder mcts)
+options:
+{}
+  --board array|bitboard|both
+                    board representation; both compares the speed of the two (default: bitboard)
+  --games N         number of games for the winning rate; game i starts from a board with {} random moves
+                    decided by i, and is played twice swapping sides (default: 100)
+  --jobs N          number of threads playing games in parallel (default: 1)
+{}
+  --seed N|none     seed of the random numbers of the AIs; none seeds them from entropy (default: 0)",
+        ALTERNATE_AI_NAMES.join(", "),
+        alternate_ai_options_usage(DEFAULT_DEPTH),
+        OPENING_MOVES,
+        SPRT_OPTIONS_USAGE
+    )
+}
+
+fn run_with_board<S: ConnectFourState + 'static>(args: &Args, board: &str, play: bool) -> Result<(), String> {
+    let new_ais = ai_factory(args, |args| {
+        Ok([
+            alternate_ai::<S>(args.ai_name(0, "thunder"), args, DEFAULT_DEPTH)?,
+            alternate_ai::<S>(args.ai_name(1, "mcts"), args, DEFAULT_DEPTH)?,
+        ])
+    })?;
+    let ais = new_ais();
+    let game_number = args.get("games", 100)?;
+    let thread_number = args.get("jobs", 1)?;
+    let sprt = args.get_sprt()?;
+    let seed = args.get_seed(Some(0))?;
+
+    println!("{} board", board);
+    let states = get_sample_states::<S>(100);
+    calculate_execution_speed(&ais[0], &states, &mut new_rng(seed));
+    calculate_execution_speed(&ais[1], &states, &mut new_rng(seed));
+
+    if play {
+        test_first_player_win_rate(&new_ais, game_number, seed, thread_number, sprt);
+        playGame(&ais, S::new(), &mut new_rng(seed));
+    }
+    Ok(())
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    match args.get("board", "bitboard".to_string())?.as_str() {
+        "array" => run_with_board::<ArrayState>(args, "array", true),
+        "bitboard" => run_with_board::<BitboardState>(args, "bitboard", true),
+        "both" => {
+            run_with_board::<ArrayState>(args, "array", false)?;
+            run_with_board::<BitboardState>(args, "bitboard", false)
+        }
+        board => Err(format!("unknown board: {}", board)),
+    }
+}
+
+fn main() {
+    let known_options =
+        [ALTERNATE_AI_OPTIONS.as_slice(), SPRT_OPTIONS.as_slice(), &["board", "games", "jobs", "seed"]].concat();
+    let args = Args::from_env_or_exit(&known_options, &usage());
+    if let Err(e) = run(&args) {
+        exit_with_usage(&usage(), Some(&e));
+    }
+}
@@ -0,0 +1,65 @@
+// 2人交互ゲームのAIを総当たりで対戦させ、対戦表とElo ratingを表示する
+
+use thunder_book::cli::{
+    ai_factory, alternate_ai, alternate_ai_options_usage, exit_with_usage, Args, ALTERNATE_AI_NAMES,
+    ALTERNATE_AI_OPTIONS,
+};
+use thunder_book::tournament::round_robin;
+use thunder_book::{ch5, ch8, AlternateGameState, HashedGameState};
+
+const DEFAULT_AI_NAMES: [&str; 5] = ["random", "min-max", "alpha-beta", "mcts", "thunder"];
+
+fn usage() -> String {
+    format!(
+        "usage: tournament [AI...] [options]
+AI: {} (default: {})
+options:
+{}
+  --game maze|connect-four
+                    game of chapter5 or chapter8; game i of a pair starts from the maze of seed i or
+                    from a connect four board with {} random moves decided by i (default: maze)
+  --games N         number of games per pair; each game is played twice swapping sides (default: 10)
+  --jobs N          number of threads playing games in parallel (default: 1)
+  --seed N|none     seed of the random numbers of the AIs; none seeds them from entropy (default: 0)",
+        ALTERNATE_AI_NAMES.join(", "),
+        DEFAULT_AI_NAMES.join(" "),
+        alternate_ai_options_usage(ch5::END_TURN),
+        ch8::OPENING_MOVES
+    )
+}
+
+fn run_with_game<S: AlternateGameState + HashedGameState + Send + Sync + 'static>(
+    args: &Args,
+    default_depth: usize,
+    new_state: impl Fn(usize) -> S + Sync,
+) -> Result<(), String> {
+    let new_ais = ai_factory(args, move |args| {
+        let names: Vec<&str> = if args.positional.is_empty() {
+            DEFAULT_AI_NAMES.to_vec()
+        } else {
+            args.positional.iter().map(String::as_str).collect()
+        };
+        names.into_iter().map(|name| alternate_ai::<S>(name, args, default_depth)).collect()
+    })?;
+    let game_number = args.get("games", 10)?;
+    let thread_number = args.get("jobs", 1)?;
+    let seed = args.get_seed(Some(0))?;
+    round_robin(&new_ais, game_number, new_state, seed, thread_number).to_string();
+    Ok(())
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    match args.get("game", "maze".to_string())?.as_str() {
+        "maze" => run_with_game(args, ch5::END_TURN, |i| ch5::MazeState::new(Some(i as u64))),
+        "connect-four" => run_with_game(args, 4, ch8::opening_state::<ch8::BitboardState>),
+        game => Err(format!("unknown game: {}", game)),
+    }
+}
+
+fn main() {
+    let known_options = [ALTERNATE_AI_OPTIONS.as_slice(), &["game", "games", "jobs", "seed"]].concat();
+    let args = Args::from_env_or_exit(&known_options, &usage());
+    if let Err(e) = run(&args) {
+        exit_with_usage(&usage(), Some(&e));
+    }
+}
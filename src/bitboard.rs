@@ -0,0 +1,84 @@
+// 盤面の各マスを1ビットで表すビットボード
+// 1行を1つのu64で表し、上下左右への移動をシフトとマスクでまとめて計算する
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BitBoard<const H: usize, const W: usize> {
+    pub rows: [u64; H],
+}
+impl<const H: usize, const W: usize> Default for BitBoard<H, W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<const H: usize, const W: usize> BitBoard<H, W> {
+    const ROW_MASK: u64 = if W == 64 { u64::MAX } else { (1 << W) - 1 };
+
+    pub fn new() -> Self {
+        assert!(W <= 64);
+        BitBoard { rows: [0; H] }
+    }
+
+    #[inline]
+    pub fn get(&self, y: usize, x: usize) -> bool {
+        self.rows[y] >> x & 1 == 1
+    }
+
+    #[inline]
+    pub fn set(&mut self, y: usize, x: usize) {
+        self.rows[y] |= 1 << x;
+    }
+
+    #[inline]
+    pub fn del(&mut self, y: usize, x: usize) {
+        self.rows[y] &= !(1 << x);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.iter().all(|&row| row == 0)
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.rows.iter().zip(other.rows.iter()).any(|(a, b)| a & b != 0)
+    }
+
+    pub fn and_not(&self, other: &Self) -> Self {
+        let mut rows = self.rows;
+        for (row, other_row) in rows.iter_mut().zip(other.rows.iter()) {
+            *row &= !other_row;
+        }
+        BitBoard { rows }
+    }
+
+    // 立っているビットを上下左右に1マスずつ広げる(元のビットも残す)
+    pub fn expand(&self) -> Self {
+        let mut rows = [0; H];
+        for (y, expanded_row) in rows.iter_mut().enumerate() {
+            let row = self.rows[y];
+            let mut expanded = row | (row << 1) | (row >> 1);
+            if y > 0 {
+                expanded |= self.rows[y - 1];
+            }
+            if y + 1 < H {
+                expanded |= self.rows[y + 1];
+            }
+            *expanded_row = expanded & Self::ROW_MASK;
+        }
+        BitBoard { rows }
+    }
+
+    // startから壁を避けて1マスずつ広げていき、targetsのいずれかに届くまでの距離
+    // 1回の拡張で同じ距離のマスを全て同時に処理する
+    pub fn distance_to_nearest(start: &Self, walls: &Self, targets: &Self) -> Option<usize> {
+        let mut reached = *start;
+        let mut distance = 0;
+        while !reached.intersects(targets) {
+            let next = reached.expand().and_not(walls);
+            if next == reached {
+                return None;
+            }
+            reached = next;
+            distance += 1;
+        }
+        Some(distance)
+    }
+}
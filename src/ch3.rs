@@ -0,0 +1,271 @@
+// chapter3: 1人で盤面の数字を集める迷路ゲーム
+
+use rand::Rng;
+use std::sync::Arc;
+
+use crate::maze_text::{check_moves, MazeText};
+use crate::random::game_seed;
+use crate::search::{greedy_action, take_dropped};
+use crate::statistics::Summary;
+use crate::worker_pool::run_tasks;
+use crate::zobrist::ZobristTable;
+use crate::{
+    dx, dy, new_rng, AIFactory, AIFunction, Coord, GameState, HashedGameState, RandomGenerator, ScoreType, TimeKeeper,
+};
+
+// 盤面の大きさ、終了ターン、マスに置かれる点数の最大値
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MazeConfig {
+    pub height: usize,
+    pub width: usize,
+    pub end_turn: usize,
+    pub max_point: usize,
+}
+impl Default for MazeConfig {
+    fn default() -> Self {
+        MazeConfig {
+            height: 30,
+            width: 30,
+            end_turn: 100,
+            max_point: 9,
+        }
+    }
+}
+impl MazeConfig {
+    // 空の盤面や、1ターン以上あるのに1マスしかなく動けない盤面をエラーにする
+    pub fn validate(&self) -> Result<(), String> {
+        if self.height == 0 || self.width == 0 {
+            return Err(format!("the maze must not be empty: {}x{}", self.height, self.width));
+        }
+        check_moves(self.height, self.width, self.end_turn)
+    }
+}
+
+// hashは盤面の点数とキャラクターの位置から計算し、advanceで差分更新する
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MazeState {
+    pub config: MazeConfig,
+    pub grid: Vec<Vec<usize>>,
+    pub turn: usize,
+    pub character: Coord,
+    pub game_score: ScoreType,
+    pub zobrist: Arc<ZobristTable>,
+    pub hash: u64,
+}
+impl MazeState {
+    pub fn new(seed: Option<u64>, config: MazeConfig) -> Self {
+        let mut rng: rand::rngs::StdRng = match seed {
+            Some(seed) => rand::SeedableRng::seed_from_u64(seed),
+            None => rand::SeedableRng::from_entropy(),
+        };
+        let mut character = Coord::new();
+        character.x = rng.gen_range(0, config.width);
+        character.y = rng.gen_range(0, config.height);
+        let mut grid: Vec<Vec<usize>> = vec![vec![0; config.width]; config.height];
+        for (y, row) in grid.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if y == character.y && x == character.x {
+                    continue;
+                }
+                *cell = rng.gen_range(0, config.max_point + 1);
+            }
+        }
+        Self::from_grid(config, grid, character)
+    }
+
+    // 盤面の点数とキャラクターの位置を指定して生成する
+    pub fn from_grid(config: MazeConfig, grid: Vec<Vec<usize>>, character: Coord) -> Self {
+        let zobrist = ZobristTable::new(config.height, config.width, config.max_point, 1, config.end_turn);
+        let mut state = MazeState {
+            config,
+            grid,
+            turn: 0,
+            character,
+            game_score: 0,
+            zobrist: Arc::new(zobrist),
+            hash: 0,
+        };
+        state.hash = state.compute_hash();
+        state
+    }
+
+    pub fn from_maze_text(maze: &MazeText) -> Result<Self, String> {
+        maze.expect_characters(1)?;
+        let config = MazeConfig {
+            height: maze.height,
+            width: maze.width,
+            end_turn: maze.end_turn,
+            max_point: 9,
+        };
+        config.validate()?;
+        Ok(Self::from_grid(config, maze.grid.clone(), maze.characters[0]))
+    }
+
+    pub fn to_maze_text(&self) -> MazeText {
+        MazeText {
+            height: self.config.height,
+            width: self.config.width,
+            end_turn: self.config.end_turn - self.turn,
+            characters: vec![self.character],
+            grid: self.grid.clone(),
+        }
+    }
+
+    // 差分更新を使わずに盤面全体からハッシュを計算する
+    pub fn compute_hash(&self) -> u64 {
+        self.zobrist.grid_hash(&self.grid) ^ self.zobrist.character(0, self.character.y, self.character.x)
+    }
+
+    pub fn isIn(&self, x: isize, y: isize) -> bool {
+        x >= 0 && x < self.config.width as isize && y >= 0 && y < self.config.height as isize
+    }
+
+    pub fn to_string(&self) {
+        eprintln!("turn:{}, score:{}", self.turn, self.game_score);
+        for (y, row) in self.grid.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if y == self.character.y && x == self.character.x {
+                    eprint!("@");
+                } else {
+                    eprint!("{}", cell);
+                }
+            }
+            eprintln!();
+        }
+        eprintln!();
+    }
+}
+impl GameState for MazeState {
+    fn legal_actions(&self) -> Vec<usize> {
+        let mut actions: Vec<usize> = Vec::new();
+        for action in 0..4 {
+            let x: isize = self.character.x as isize + dx[action];
+            let y: isize = self.character.y as isize + dy[action];
+            if self.isIn(x, y) {
+                actions.push(action);
+            }
+        }
+        actions
+    }
+
+    fn advance(&mut self, action: usize) {
+        assert!(action < 4);
+        self.hash ^= self.zobrist.character(0, self.character.y, self.character.x);
+        self.character.x = (self.character.x as isize + dx[action]) as usize;
+        self.character.y = (self.character.y as isize + dy[action]) as usize;
+        assert!(self.character.x < self.config.width && self.character.y < self.config.height);
+        let point: ScoreType = self.grid[self.character.y][self.character.x] as ScoreType;
+        if point > 0 {
+            self.game_score += point;
+            self.grid[self.character.y][self.character.x] = 0;
+            self.hash ^= self.zobrist.cell(self.character.y, self.character.x, point as usize);
+        }
+        self.hash ^= self.zobrist.character(0, self.character.y, self.character.x);
+        self.turn += 1;
+    }
+
+    fn is_done(&self) -> bool {
+        assert!(self.turn <= self.config.end_turn);
+        self.turn == self.config.end_turn
+    }
+
+    fn evaluate(&self) -> ScoreType {
+        self.game_score
+    }
+}
+
+impl HashedGameState for MazeState {
+    fn zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+// 行動(dx, dyの添字)を右、下、左、上の1文字で表す
+pub const ACTION_NAMES: [char; 4] = ['R', 'D', 'L', 'U'];
+
+// 1手ごとの制限時間(秒)を受け取って行動を返すAI
+pub type TimedAIFunction = dyn Fn(&MazeState, f64) -> usize;
+
+// 全体の制限時間time_limit(秒)の中で終了までプレイし、選んだ行動の列を返す
+// 各ターンには残り時間を残りターン数で等分した時間を渡し、使い切った後は貪欲法で進める
+pub fn solve(state: &MazeState, ai: &TimedAIFunction, time_limit: f64) -> Vec<usize> {
+    let time_keeper = TimeKeeper::new(time_limit);
+    let mut state = state.clone();
+    let mut actions = Vec::new();
+    while !state.is_done() {
+        let action = if time_keeper.isTimeOver() {
+            greedy_action(&state)
+        } else {
+            let remaining_turns = state.config.end_turn - state.turn;
+            ai(&state, (time_limit - time_keeper.getElapsedTime()) / remaining_turns as f64)
+        };
+        state.advance(action);
+        actions.push(action);
+    }
+    actions
+}
+
+// aiに従って1ゲームプレイしてスコアを返す
+// AIの乱数も迷路と同じseedから作るので、seedを指定すれば同じゲームを再現できる
+pub fn play_game(ai: &AIFunction<MazeState>, seed: Option<u64>, config: MazeConfig) -> ScoreType {
+    play_state(ai, MazeState::new(seed, config), &mut new_rng(seed))
+}
+
+// stateから終了までaiに従ってプレイしてスコアを返す
+pub fn play_state(ai: &AIFunction<MazeState>, mut state: MazeState, rng: &mut RandomGenerator) -> ScoreType {
+    while !state.is_done() {
+        let action: usize = ai(&state, rng);
+        state.advance(action);
+    }
+    state.game_score
+}
+
+// ゲームをgame_number回プレイしてスコアの平均と標準偏差を返す
+// thread_number本のスレッドで並列にプレイしても、結果はseedの順に表示して集計する
+// 重複を除いた探索を使うAIなら、ゲームごとに捨てた状態の数も表示する
+pub fn test_AI_score(
+    new_ai: &AIFactory<MazeState>,
+    game_number: usize,
+    seed: Option<u64>,
+    config: MazeConfig,
+    thread_number: usize,
+) -> Summary {
+    let results = run_tasks(
+        game_number,
+        thread_number,
+        new_ai,
+        |ai, cnt| {
+            take_dropped();
+            let score = play_game(&*ai.1, game_seed(seed, cnt), config);
+            (score, take_dropped())
+        },
+        |cnt, &(score, dropped)| {
+            eprintln!("game: {} end, score:{}", cnt, score);
+            if dropped > 0 {
+                eprintln!("dropped duplicates:{}", dropped);
+            }
+        },
+    );
+    let scores: Vec<f64> = results.iter().map(|&(score, _)| score as f64).collect();
+    Summary::new(&scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::random_action;
+
+    // advanceで差分更新したハッシュが、盤面全体から計算し直したハッシュと一致する
+    #[test]
+    fn incremental_hash_matches_compute_hash_along_random_games() {
+        let mut rng = new_rng(Some(0));
+        for seed in 0..100 {
+            let mut state = MazeState::new(Some(seed), MazeConfig { height: 4, width: 5, end_turn: 20, max_point: 9 });
+            assert_eq!(state.zobrist_hash(), state.compute_hash(), "seed {} turn 0", seed);
+            while !state.is_done() {
+                state.advance(random_action(&state, &mut rng));
+                assert_eq!(state.zobrist_hash(), state.compute_hash(), "seed {} turn {}", seed, state.turn);
+            }
+        }
+    }
+}
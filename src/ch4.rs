@@ -0,0 +1,1290 @@
+// chapter4: キャラクターの初期配置だけを決め、あとは自動で動く迷路ゲーム
+
+use rand::Rng;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::maze_text::MazeText;
+use crate::statistics::Summary;
+use crate::worker_pool::run_tasks;
+use crate::random::{game_seed, split_rng};
+use crate::{dx, dy, new_rng, Coord, RandomGenerator, ScoreType, TimeKeeper};
+
+pub const H: usize = 20;
+pub const W: usize = 20;
+pub const END_TURN: usize = 50;
+pub const N_CHARACTER: usize = 3;
+
+// 初期状態を受け取ってキャラクターを配置した状態を返すAI
+pub type Ch4AIFunction = dyn Fn(&MazeState, &mut RandomGenerator) -> MazeState;
+// 並列に対戦させるとき、スレッドごとに同じAIを作り直すための関数
+pub type Ch4AIFactory = dyn Fn() -> Rc<Ch4AIFunction> + Sync;
+
+// キャラクターの数はcharacters.len()
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MazeState {
+    pub h: usize,
+    pub w: usize,
+    pub end_turn: usize,
+    pub grid: Vec<Vec<usize>>,
+    pub turn: usize,
+    pub characters: Vec<Coord>,
+    pub game_score: ScoreType,
+}
+impl MazeState {
+    pub fn new(seed: Option<u64>) -> Self {
+        Self::with_size(seed, H, W, END_TURN, N_CHARACTER)
+    }
+
+    // 盤面の大きさ、終了ターン、キャラクターの数を指定して生成する
+    pub fn with_size(seed: Option<u64>, h: usize, w: usize, end_turn: usize, character_number: usize) -> Self {
+        let mut rng: rand::rngs::StdRng = match seed {
+            Some(seed) => rand::SeedableRng::seed_from_u64(seed),
+            None => rand::SeedableRng::from_entropy(),
+        };
+        let mut grid: Vec<Vec<usize>> = vec![vec![0; w]; h];
+        for cell in grid.iter_mut().flatten() {
+            *cell = rng.gen_range(0, 10);
+        }
+        Self::from_grid(grid, end_turn, vec![Coord::new(); character_number])
+    }
+
+    // 盤面の点数とキャラクターの位置を指定して生成する
+    pub fn from_grid(grid: Vec<Vec<usize>>, end_turn: usize, characters: Vec<Coord>) -> Self {
+        MazeState {
+            h: grid.len(),
+            w: grid[0].len(),
+            end_turn,
+            grid,
+            turn: 0,
+            characters,
+            game_score: 0,
+        }
+    }
+
+    pub fn from_maze_text(maze: &MazeText) -> Result<Self, String> {
+        if maze.characters.is_empty() {
+            return Err("chapter4 needs at least one character".to_string());
+        }
+        maze.expect_moves()?;
+        Ok(Self::from_grid(maze.grid.clone(), maze.end_turn, maze.characters.clone()))
+    }
+
+    pub fn to_maze_text(&self) -> MazeText {
+        MazeText {
+            height: self.h,
+            width: self.w,
+            end_turn: self.end_turn - self.turn,
+            characters: self.characters.clone(),
+            grid: self.grid.clone(),
+        }
+    }
+
+    pub fn set_character(&mut self, id: usize, y: usize, x: usize) {
+        assert!(id < self.characters.len());
+        self.characters[id] = Coord { y, x };
+    }
+
+    // キャラクターの配置をランダムに初期化する
+    pub fn init<R: Rng>(&mut self, rng: &mut R) {
+        for id in 0..self.characters.len() {
+            let y = rng.gen_range(0, self.h);
+            let x = rng.gen_range(0, self.w);
+            self.set_character(id, y, x);
+        }
+    }
+
+    pub fn transition<R: Rng>(&mut self, rng: &mut R) {
+        let id = rng.gen_range(0, self.characters.len());
+        let y = rng.gen_range(0, self.h);
+        let x = rng.gen_range(0, self.w);
+        self.set_character(id, y, x);
+    }
+
+    pub fn get_score(&self, is_print: bool) -> ScoreType {
+        let mut tmp_state = self.clone();
+        for character in self.characters.iter() {
+            tmp_state.grid[character.y][character.x] = 0;
+        }
+        if is_print {
+            tmp_state.to_string();
+        }
+        while !tmp_state.is_done() {
+            tmp_state.advance();
+            if is_print {
+                tmp_state.to_string();
+            }
+        }
+        tmp_state.game_score
+    }
+
+    pub fn is_done(&self) -> bool {
+        assert!(self.turn <= self.end_turn);
+        self.turn == self.end_turn
+    }
+
+    pub fn move_player(&mut self, id: usize) {
+        assert!(id < self.characters.len());
+        let mut best_point: ScoreType = -1;
+        let mut best_action = 0;
+        for action in 0..4 {
+            let ny = self.characters[id].y as isize + dy[action];
+            let nx = self.characters[id].x as isize + dx[action];
+            if !self.isIn(nx, ny) {
+                continue;
+            }
+            let point = self.grid[ny as usize][nx as usize] as ScoreType;
+            if point > best_point {
+                best_point = point;
+                best_action = action;
+            }
+        }
+        self.characters[id].y = (self.characters[id].y as isize + dy[best_action]) as usize;
+        self.characters[id].x = (self.characters[id].x as isize + dx[best_action]) as usize;
+    }
+
+    pub fn advance(&mut self) {
+        for id in 0..self.characters.len() {
+            self.move_player(id);
+        }
+        for character in self.characters.iter() {
+            self.game_score += self.grid[character.y][character.x] as ScoreType;
+            self.grid[character.y][character.x] = 0;
+        }
+        self.turn += 1;
+    }
+
+    pub fn isIn(&self, x: isize, y: isize) -> bool {
+        x >= 0 && x < self.w as isize && y >= 0 && y < self.h as isize
+    }
+
+    pub fn to_string(&self) {
+        eprintln!("turn:{}, score:{}", self.turn, self.game_score);
+        let mut str: Vec<Vec<char>> = self
+            .grid
+            .iter()
+            .map(|row| row.iter().map(|&cell| std::char::from_digit(cell as u32, 10).unwrap()).collect())
+            .collect();
+        for character in self.characters.iter() {
+            str[character.y][character.x] = '@';
+        }
+        for row in str.iter() {
+            eprintln!("{}", row.iter().collect::<String>());
+        }
+        eprintln!();
+    }
+}
+
+// get_scoreと同じスコアを、各キャラクターが1人だけで動いた場合の軌跡を使って差分計算する
+// 1人だけの軌跡は開始位置だけで決まるのでマスごとにキャッシュしておき、
+// 軌跡どうしが干渉しうる最初のターンからだけ全員を一緒に動かす
+// 盤面の点数が同じ状態(キャラクターの配置だけが違う状態)にだけ使える
+// 軌跡はほとんどの配置で途中から干渉するので、速さはget_scoreの1.6倍程度が上限になる(ch4 --evaluator-speedで測れる)
+#[derive(Clone, Debug)]
+pub struct IncrementalEvaluator {
+    w: usize,
+    turn_number: usize,
+    game_score: ScoreType,
+    // マスはy * w + xの添字で表す
+    points: Vec<ScoreType>,
+    // neighbors[cell][action]は行動actionで移動する先のマス。盤面の外ならusize::MAX
+    neighbors: Vec<[usize; 4]>,
+    // trajectories[cell]はそのマスから1人だけで動いたときの各ターンの位置
+    trajectories: Vec<Option<Vec<usize>>>,
+    // first_visits[id * cell_number + cell]はキャラクターidが1人だけの軌跡で初めてそのマスにいたターン
+    // 呼び出しのたびに使ったところだけusize::MAXに戻す
+    first_visits: Vec<usize>,
+    // 作業用の盤面
+    grid: Vec<ScoreType>,
+}
+impl IncrementalEvaluator {
+    pub fn new(state: &MazeState) -> Self {
+        let cell_number = state.h * state.w;
+        let mut neighbors = vec![[usize::MAX; 4]; cell_number];
+        for (cell, neighbor) in neighbors.iter_mut().enumerate() {
+            let (y, x) = ((cell / state.w) as isize, (cell % state.w) as isize);
+            for action in 0..4 {
+                let (ny, nx) = (y + dy[action], x + dx[action]);
+                if state.isIn(nx, ny) {
+                    neighbor[action] = ny as usize * state.w + nx as usize;
+                }
+            }
+        }
+        IncrementalEvaluator {
+            w: state.w,
+            turn_number: state.end_turn - state.turn,
+            game_score: state.game_score,
+            points: state.grid.iter().flatten().map(|&point| point as ScoreType).collect(),
+            neighbors,
+            trajectories: vec![None; cell_number],
+            first_visits: vec![usize::MAX; state.characters.len() * cell_number],
+            grid: vec![0; cell_number],
+        }
+    }
+
+    // gridを盤面の点数に戻し、charactersのいるマスを0にする
+    fn reset_grid(&mut self, characters: &[usize]) {
+        self.grid.copy_from_slice(&self.points);
+        for &cell in characters.iter() {
+            self.grid[cell] = 0;
+        }
+    }
+
+    fn compute_trajectory(&mut self, start: usize) {
+        if self.trajectories[start].is_some() {
+            return;
+        }
+        let mut character = [start];
+        self.reset_grid(&character);
+        let mut trajectory = vec![start];
+        simulate(&self.neighbors, &mut self.grid, 0..self.turn_number, &mut character, |character| {
+            trajectory.push(character[0])
+        });
+        self.trajectories[start] = Some(trajectory);
+    }
+
+    pub fn get_score(&mut self, state: &MazeState) -> ScoreType {
+        let (n, cell_number, turn_number) = (state.characters.len(), self.neighbors.len(), self.turn_number);
+        let starts: Vec<usize> = state.characters.iter().map(|character| character.y * self.w + character.x).collect();
+        for &start in starts.iter() {
+            self.compute_trajectory(start);
+        }
+        let trajectories: Vec<&[usize]> =
+            starts.iter().map(|&start| self.trajectories[start].as_deref().unwrap()).collect();
+        let first_visits = &mut self.first_visits;
+        for (id, trajectory) in trajectories.iter().enumerate() {
+            for (t, &cell) in trajectory.iter().enumerate().rev() {
+                first_visits[id * cell_number + cell] = t;
+            }
+        }
+
+        // 移動先は点数が最大の隣のマスなので、ターンtの移動先が、自分はまだ取っていないが
+        // 他のキャラクターが既に取った点数のあるマスでなければ、他のマスが0になっても移動先は変わらない
+        let until = (0..turn_number)
+            .find(|&t| {
+                trajectories.iter().enumerate().any(|(id, trajectory)| {
+                    let next = trajectory[t + 1];
+                    first_visits[id * cell_number + next] == t + 1
+                        && self.points[next] > 0
+                        && (0..n).any(|other| other != id && first_visits[other * cell_number + next] <= t)
+                })
+            })
+            .unwrap_or(turn_number);
+
+        // untilターンまでは1人だけの軌跡どおりに動くので、そこまでに取ったマスの点数を足して続きを動かす
+        // 最初にいたマスは点数を得ないまま0になる
+        let mut characters = starts;
+        self.grid.copy_from_slice(&self.points);
+        for &cell in characters.iter() {
+            self.grid[cell] = 0;
+        }
+        let mut score = self.game_score;
+        for t in 1..=until {
+            for (character, trajectory) in characters.iter_mut().zip(trajectories.iter()) {
+                *character = trajectory[t];
+                score += self.grid[*character];
+                self.grid[*character] = 0;
+            }
+        }
+        score += simulate(&self.neighbors, &mut self.grid, until..turn_number, &mut characters, |_| {});
+
+        for (id, trajectory) in trajectories.iter().enumerate() {
+            for &cell in trajectory.iter() {
+                first_visits[id * cell_number + cell] = usize::MAX;
+            }
+        }
+        score
+    }
+}
+
+// move_playerと同じく、点数が最大の隣のマスに移動する。同点なら行動の添字が小さい方
+// pointはそのマスの今の点数
+fn next_cell(neighbors: &[[usize; 4]], cell: usize, point: impl Fn(usize) -> ScoreType) -> usize {
+    let mut best_point: ScoreType = -1;
+    let mut best_cell = cell;
+    for &next in neighbors[cell].iter() {
+        if next == usize::MAX {
+            continue;
+        }
+        let point = point(next);
+        if point > best_point {
+            best_point = point;
+            best_cell = next;
+        }
+    }
+    best_cell
+}
+
+// advanceと同じく全員をturnsのターン数だけ動かし、取ったマスの点数を返す
+// gridは最初のターンまでに取られたマスを0にした盤面の点数で、ターンごとに動いた後の位置をon_turnに渡す
+fn simulate(
+    neighbors: &[[usize; 4]],
+    grid: &mut [ScoreType],
+    turns: std::ops::Range<usize>,
+    characters: &mut [usize],
+    mut on_turn: impl FnMut(&[usize]),
+) -> ScoreType {
+    let mut score = 0;
+    for _ in turns {
+        for character in characters.iter_mut() {
+            *character = next_cell(neighbors, *character, |cell| grid[cell]);
+        }
+        for &character in characters.iter() {
+            score += grid[character];
+            grid[character] = 0;
+        }
+        on_turn(characters);
+    }
+    score
+}
+
+// ランダムな配置から1人ずつ動かした配置をnumber個作り、get_scoreとIncrementalEvaluatorで
+// 評価するのにかかった時間(秒)を返す
+pub fn evaluator_speed<R: Rng>(state: &MazeState, number: usize, rng: &mut R) -> (f64, f64) {
+    let mut now_state = random_action(state, rng);
+    let states: Vec<MazeState> = (0..number)
+        .map(|_| {
+            now_state.transition(rng);
+            now_state.clone()
+        })
+        .collect();
+    let start_time = std::time::Instant::now();
+    let full_scores: Vec<ScoreType> = states.iter().map(|state| state.get_score(false)).collect();
+    let full_time = start_time.elapsed().as_secs_f64();
+    let start_time = std::time::Instant::now();
+    let mut evaluator = IncrementalEvaluator::new(state);
+    let incremental_scores: Vec<ScoreType> = states.iter().map(|state| evaluator.get_score(state)).collect();
+    let incremental_time = start_time.elapsed().as_secs_f64();
+    assert_eq!(full_scores, incremental_scores);
+    (full_time, incremental_time)
+}
+
+pub fn random_action<R: Rng>(state: &MazeState, rng: &mut R) -> MazeState {
+    let mut now_state = state.clone();
+    for id in 0..state.characters.len() {
+        let y = rng.gen_range(0, state.h);
+        let x = rng.gen_range(0, state.w);
+        now_state.set_character(id, y, x);
+    }
+    now_state
+}
+
+// 配置を少しだけ変える近傍の操作
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NeighborOperator {
+    // 1人を一様ランダムなマスに動かす(transitionと同じ)
+    TELEPORT,
+    // 1人を縦横それぞれradius以内のマスに動かす
+    SHIFT,
+    // 1人を点数の高いマスから縦横それぞれradius以内のマスに動かす
+    HOTSPOT,
+}
+pub const NEIGHBOR_OPERATORS: [NeighborOperator; 3] =
+    [NeighborOperator::TELEPORT, NeighborOperator::SHIFT, NeighborOperator::HOTSPOT];
+impl NeighborOperator {
+    pub fn name(self) -> &'static str {
+        match self {
+            NeighborOperator::TELEPORT => "teleport",
+            NeighborOperator::SHIFT => "shift",
+            NeighborOperator::HOTSPOT => "hotspot",
+        }
+    }
+}
+
+// 近傍の操作を選ぶ重み(NEIGHBOR_OPERATORSの順)と、SHIFTとHOTSPOTで動かす距離
+// 既定ではTELEPORTだけを使う
+#[derive(Clone, Debug, PartialEq)]
+pub struct Neighborhood {
+    pub weights: [f64; 3],
+    pub radius: usize,
+}
+impl Default for Neighborhood {
+    fn default() -> Self {
+        Neighborhood {
+            weights: [1.0, 0.0, 0.0],
+            radius: 2,
+        }
+    }
+}
+impl Neighborhood {
+    // "teleport:1,shift:3"のように操作の名前と重みを並べたものを読む。書かなかった操作の重みは0
+    pub fn parse(text: &str, radius: usize) -> Result<Self, String> {
+        let mut weights = [0.0; 3];
+        for item in text.split(',') {
+            let Some((name, weight)) = item.split_once(':') else {
+                return Err(format!("operator needs NAME:WEIGHT: {}", item));
+            };
+            // キャラクターには区別がないので、2人の位置を入れ替えてもスコアは変わらない
+            if name.trim() == "swap" {
+                return Err("swap is not supported: exchanging two characters never changes the score".to_string());
+            }
+            let Some(index) = NEIGHBOR_OPERATORS.iter().position(|operator| operator.name() == name.trim()) else {
+                return Err(format!("unknown operator: {}", name.trim()));
+            };
+            let weight: f64 = weight.trim().parse().map_err(|_| format!("invalid weight of {}: {}", name, weight))?;
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(format!("weight of {} must be non-negative: {}", name, weight));
+            }
+            weights[index] = weight;
+        }
+        if weights.iter().sum::<f64>() <= 0.0 {
+            return Err("at least one operator needs a positive weight".to_string());
+        }
+        if radius == 0 && weights[NeighborOperator::SHIFT as usize] > 0.0 {
+            return Err("shift needs a positive radius".to_string());
+        }
+        Ok(Neighborhood { weights, radius })
+    }
+
+    // 重みに比例した確率で操作を選ぶ
+    // 選べる操作が1つなら乱数を使わないので、TELEPORTだけならtransitionと同じ乱数列になる
+    fn choose<R: Rng>(&self, rng: &mut R) -> NeighborOperator {
+        let mut candidates = (0..NEIGHBOR_OPERATORS.len()).filter(|&index| self.weights[index] > 0.0);
+        if let (Some(index), None) = (candidates.next(), candidates.next()) {
+            return NEIGHBOR_OPERATORS[index];
+        }
+        let mut r = rng.gen::<f64>() * self.weights.iter().sum::<f64>();
+        for (index, &weight) in self.weights.iter().enumerate() {
+            if weight > 0.0 && r < weight {
+                return NEIGHBOR_OPERATORS[index];
+            }
+            r -= weight;
+        }
+        let last = self.weights.iter().rposition(|&weight| weight > 0.0).unwrap();
+        NEIGHBOR_OPERATORS[last]
+    }
+}
+
+// 近傍の状態を作る。HOTSPOTの候補にする点数の高いマス(盤面の1割)は最初に求めておく
+pub struct NeighborGenerator<'a> {
+    neighborhood: &'a Neighborhood,
+    high_cells: Vec<Coord>,
+}
+impl<'a> NeighborGenerator<'a> {
+    pub fn new(neighborhood: &'a Neighborhood, state: &MazeState) -> Self {
+        let mut cells: Vec<Coord> =
+            (0..state.h).flat_map(|y| (0..state.w).map(move |x| Coord { y, x })).collect();
+        cells.sort_by_key(|cell| std::cmp::Reverse(state.grid[cell.y][cell.x]));
+        cells.truncate((state.h * state.w / 10).max(1));
+        NeighborGenerator {
+            neighborhood,
+            high_cells: cells,
+        }
+    }
+
+    // stateに選んだ操作を1回行った状態を、操作と一緒に返す
+    pub fn generate<R: Rng>(&self, state: &MazeState, rng: &mut R) -> (NeighborOperator, MazeState) {
+        let operator = self.neighborhood.choose(rng);
+        let mut next_state = state.clone();
+        let n = state.characters.len();
+        match operator {
+            NeighborOperator::TELEPORT => next_state.transition(rng),
+            NeighborOperator::SHIFT => {
+                let id = rng.gen_range(0, n);
+                let character = state.characters[id];
+                let (y, x) = self.shift(state, character, rng);
+                next_state.set_character(id, y, x);
+            }
+            NeighborOperator::HOTSPOT => {
+                let id = rng.gen_range(0, n);
+                let center = self.high_cells[rng.gen_range(0, self.high_cells.len())];
+                let (y, x) = self.shift(state, center, rng);
+                next_state.set_character(id, y, x);
+            }
+        }
+        (operator, next_state)
+    }
+
+    // centerから縦横それぞれradius以内のランダムなマス。盤面の外に出る分は端に寄せる
+    fn shift<R: Rng>(&self, state: &MazeState, center: Coord, rng: &mut R) -> (usize, usize) {
+        let radius = self.neighborhood.radius as isize;
+        let y = center.y as isize + rng.gen_range(-radius, radius + 1);
+        let x = center.x as isize + rng.gen_range(-radius, radius + 1);
+        (y.clamp(0, state.h as isize - 1) as usize, x.clamp(0, state.w as isize - 1) as usize)
+    }
+}
+
+// 近傍の操作ごとに、試した回数と採用した回数を数える
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OperatorStats {
+    pub tried: [usize; 3],
+    pub accepted: [usize; 3],
+}
+impl OperatorStats {
+    pub fn record(&mut self, operator: NeighborOperator, is_accepted: bool) {
+        self.tried[operator as usize] += 1;
+        if is_accepted {
+            self.accepted[operator as usize] += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &OperatorStats) {
+        for index in 0..NEIGHBOR_OPERATORS.len() {
+            self.tried[index] += other.tried[index];
+            self.accepted[index] += other.accepted[index];
+        }
+    }
+
+    pub fn acceptance_rate(&self, operator: NeighborOperator) -> f64 {
+        let index = operator as usize;
+        if self.tried[index] == 0 {
+            return 0.0;
+        }
+        self.accepted[index] as f64 / self.tried[index] as f64
+    }
+}
+// 一度も試していない操作は表示しない
+impl std::fmt::Display for OperatorStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let items: Vec<String> = NEIGHBOR_OPERATORS
+            .iter()
+            .filter(|&&operator| self.tried[operator as usize] > 0)
+            .map(|&operator| {
+                format!(
+                    "{} {:.1}% ({}/{})",
+                    operator.name(),
+                    100.0 * self.acceptance_rate(operator),
+                    self.accepted[operator as usize],
+                    self.tried[operator as usize]
+                )
+            })
+            .collect();
+        write!(f, "{}", items.join(", "))
+    }
+}
+
+thread_local! {
+    // このスレッドで局所探索のAIが記録した操作ごとの採用数の累計
+    static OPERATOR_STATS: RefCell<OperatorStats> = RefCell::new(OperatorStats::default());
+}
+
+// 局所探索のAIが1ゲーム分の操作ごとの採用数を記録する
+pub fn record_operator_stats(stats: &OperatorStats) {
+    OPERATOR_STATS.with(|total| total.borrow_mut().merge(stats));
+}
+
+// このスレッドで記録した操作ごとの採用数の累計を返してリセットする
+pub fn take_operator_stats() -> OperatorStats {
+    OPERATOR_STATS.with(|total| total.take())
+}
+
+// 局所探索の進み具合。ループのloop_cnt回目の進み具合(0から1)を返し、打ち切るならNoneを返す
+fn iteration_progress(number: usize) -> impl FnMut(usize) -> Option<f64> {
+    move |loop_cnt| (loop_cnt < number).then(|| loop_cnt as f64 / number as f64)
+}
+
+// 呼び出してからの経過時間で測る進み具合
+fn time_progress(time_threshold: f64) -> impl FnMut(usize) -> Option<f64> {
+    let time_keeper = TimeKeeper::new(time_threshold);
+    move |_| (!time_keeper.isTimeOver()).then(|| (time_keeper.getElapsedTime() / time_threshold).min(1.0))
+}
+
+// neighborhoodの操作で作った近傍が良くなったときだけ採用する
+// 最後の配置と、操作ごとの採用数を返す
+pub fn hill_climb<R: Rng>(
+    state: &MazeState,
+    number: usize,
+    neighborhood: &Neighborhood,
+    rng: &mut R,
+) -> (MazeState, OperatorStats) {
+    hill_climb_with_progress(state, neighborhood, rng, iteration_progress(number))
+}
+
+// hill_climbを反復回数ではなくtime_threshold秒で打ち切る
+pub fn hill_climb_with_time_threshold<R: Rng>(
+    state: &MazeState,
+    time_threshold: f64,
+    neighborhood: &Neighborhood,
+    rng: &mut R,
+) -> (MazeState, OperatorStats) {
+    hill_climb_with_progress(state, neighborhood, rng, time_progress(time_threshold))
+}
+
+fn hill_climb_with_progress<R: Rng>(
+    state: &MazeState,
+    neighborhood: &Neighborhood,
+    rng: &mut R,
+    mut progress: impl FnMut(usize) -> Option<f64>,
+) -> (MazeState, OperatorStats) {
+    let mut evaluator = IncrementalEvaluator::new(state);
+    let generator = NeighborGenerator::new(neighborhood, state);
+    let mut stats = OperatorStats::default();
+    let mut now_state = state.clone();
+    now_state.init(rng);
+    let mut best_score = evaluator.get_score(&now_state);
+    let mut loop_cnt = 0;
+    while progress(loop_cnt).is_some() {
+        loop_cnt += 1;
+        let (operator, next_state) = generator.generate(&now_state, rng);
+        let next_score = evaluator.get_score(&next_state);
+        let is_accepted = next_score > best_score;
+        stats.record(operator, is_accepted);
+        if is_accepted {
+            now_state = next_state;
+            best_score = next_score;
+        }
+    }
+    (now_state, stats)
+}
+
+// 焼きなましの温度の下げ方。progressは探索の進み具合(0から1)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    // start_tempからend_tempまで線形に下げる
+    LINEAR,
+    // start_tempからend_tempまで一定の比率で下げる(どちらも正)
+    EXPONENTIAL,
+    // log(1 + 99 * progress)に比例して下げる。はじめに速く下がり、終盤はゆっくり下がる
+    LOGARITHMIC,
+    // 探索をcycles個に分け、それぞれでstart_tempからend_tempまで線形に下げる
+    REHEATING(usize),
+}
+impl Schedule {
+    // linear, exponential, logarithmic, reheating:CYCLES
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.split_once(':') {
+            None if text == "linear" => Ok(Schedule::LINEAR),
+            None if text == "exponential" => Ok(Schedule::EXPONENTIAL),
+            None if text == "logarithmic" => Ok(Schedule::LOGARITHMIC),
+            Some(("reheating", cycles)) => match cycles.parse() {
+                Ok(cycles) if cycles > 0 => Ok(Schedule::REHEATING(cycles)),
+                _ => Err(format!("reheating needs a positive number of cycles: {}", cycles)),
+            },
+            _ => Err(format!("unknown schedule: {}", text)),
+        }
+    }
+}
+
+// 悪くなる近傍を受け入れるかの決め方。diffは目的の向きに直した悪化の幅(正)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Acceptance {
+    // 今のスコアからの悪化をexp(-diff / 温度)の確率で受け入れる
+    METROPOLIS,
+    // 今のスコアからの悪化が温度以下なら受け入れる
+    THRESHOLD,
+    // 今のスコアかlength回前の今のスコア以上なら受け入れる(late acceptance hill climbing)。温度は使わない
+    LATE(usize),
+    // 最も良いスコアから温度を引いた水位以上なら受け入れる(great deluge)
+    DELUGE,
+}
+impl Acceptance {
+    // metropolis, threshold, late:LENGTH, deluge
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.split_once(':') {
+            None if text == "metropolis" => Ok(Acceptance::METROPOLIS),
+            None if text == "threshold" => Ok(Acceptance::THRESHOLD),
+            None if text == "deluge" => Ok(Acceptance::DELUGE),
+            Some(("late", length)) => match length.parse() {
+                Ok(length) if length > 0 => Ok(Acceptance::LATE(length)),
+                _ => Err(format!("late acceptance needs a positive history length: {}", length)),
+            },
+            _ => Err(format!("unknown acceptance rule: {}", text)),
+        }
+    }
+}
+
+// スコアを大きくしたいか小さくしたいか
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Objective {
+    MAXIMIZE,
+    MINIMIZE,
+}
+impl Objective {
+    // max, min
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "max" => Ok(Objective::MAXIMIZE),
+            "min" => Ok(Objective::MINIMIZE),
+            _ => Err(format!("unknown objective: {}", text)),
+        }
+    }
+
+    // fromからtoに変えたときの改善の幅
+    pub fn gain(self, from: ScoreType, to: ScoreType) -> ScoreType {
+        match self {
+            Objective::MAXIMIZE => to - from,
+            Objective::MINIMIZE => from - to,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnnealingConfig {
+    pub start_temp: f64,
+    pub end_temp: f64,
+    pub schedule: Schedule,
+    pub acceptance: Acceptance,
+    pub objective: Objective,
+}
+impl Default for AnnealingConfig {
+    fn default() -> Self {
+        AnnealingConfig {
+            start_temp: 100.0,
+            end_temp: 0.0,
+            schedule: Schedule::LINEAR,
+            acceptance: Acceptance::METROPOLIS,
+            objective: Objective::MAXIMIZE,
+        }
+    }
+}
+impl AnnealingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.start_temp >= 0.0 && self.end_temp >= 0.0) {
+            return Err(format!("temperatures must be non-negative: {} {}", self.start_temp, self.end_temp));
+        }
+        if self.schedule == Schedule::EXPONENTIAL && !(self.start_temp > 0.0 && self.end_temp > 0.0) {
+            return Err("the exponential schedule needs positive start and end temperatures".to_string());
+        }
+        Ok(())
+    }
+
+    // 進み具合progress(0から1)での温度
+    pub fn temperature(&self, progress: f64) -> f64 {
+        let (start, end) = (self.start_temp, self.end_temp);
+        match self.schedule {
+            Schedule::LINEAR => start + (end - start) * progress,
+            Schedule::EXPONENTIAL => start * (end / start).powf(progress),
+            Schedule::LOGARITHMIC => start + (end - start) * (1.0 + 99.0 * progress).ln() / 100f64.ln(),
+            Schedule::REHEATING(cycles) => {
+                let cycle_progress = if progress >= 1.0 { 1.0 } else { (progress * cycles as f64).fract() };
+                start + (end - start) * cycle_progress
+            }
+        }
+    }
+}
+
+// acceptanceに従って近傍を受け入れるかを決める。LATEでは今のスコアの履歴を持つ
+struct Acceptor {
+    acceptance: Acceptance,
+    objective: Objective,
+    history: Vec<ScoreType>,
+    iteration: usize,
+}
+impl Acceptor {
+    fn new(config: &AnnealingConfig, initial_score: ScoreType) -> Self {
+        let length = match config.acceptance {
+            Acceptance::LATE(length) => length,
+            _ => 0,
+        };
+        Acceptor {
+            acceptance: config.acceptance,
+            objective: config.objective,
+            history: vec![initial_score; length],
+            iteration: 0,
+        }
+    }
+
+    fn accept<R: Rng>(
+        &mut self,
+        now_score: ScoreType,
+        next_score: ScoreType,
+        best_score: ScoreType,
+        temp: f64,
+        rng: &mut R,
+    ) -> bool {
+        let gain = self.objective.gain(now_score, next_score);
+        match self.acceptance {
+            Acceptance::METROPOLIS => gain >= 0 || rng.gen::<f64>() < (gain as f64 / temp).exp(),
+            Acceptance::THRESHOLD => -gain as f64 <= temp,
+            Acceptance::LATE(length) => {
+                let index = self.iteration % length;
+                self.iteration += 1;
+                let is_accepted = gain >= 0 || self.objective.gain(self.history[index], next_score) >= 0;
+                self.history[index] = if is_accepted { next_score } else { now_score };
+                is_accepted
+            }
+            Acceptance::DELUGE => self.objective.gain(best_score, next_score) as f64 >= -temp,
+        }
+    }
+}
+
+// configの温度の下げ方と受け入れ方で焼きなましをして、最も良かった配置と、操作ごとの採用数を返す
+pub fn simulated_annealing<R: Rng>(
+    state: &MazeState,
+    number: usize,
+    config: &AnnealingConfig,
+    neighborhood: &Neighborhood,
+    rng: &mut R,
+) -> (MazeState, OperatorStats) {
+    simulated_annealing_with_progress(state, config, neighborhood, rng, iteration_progress(number))
+}
+
+// simulated_annealingをtime_threshold秒で打ち切る。温度は経過時間の割合から決める
+pub fn simulated_annealing_with_time_threshold<R: Rng>(
+    state: &MazeState,
+    time_threshold: f64,
+    config: &AnnealingConfig,
+    neighborhood: &Neighborhood,
+    rng: &mut R,
+) -> (MazeState, OperatorStats) {
+    simulated_annealing_with_progress(state, config, neighborhood, rng, time_progress(time_threshold))
+}
+
+fn simulated_annealing_with_progress<R: Rng>(
+    state: &MazeState,
+    config: &AnnealingConfig,
+    neighborhood: &Neighborhood,
+    rng: &mut R,
+    mut progress: impl FnMut(usize) -> Option<f64>,
+) -> (MazeState, OperatorStats) {
+    let mut evaluator = IncrementalEvaluator::new(state);
+    let generator = NeighborGenerator::new(neighborhood, state);
+    let mut stats = OperatorStats::default();
+    let mut now_state = state.clone();
+    now_state.init(rng);
+    let mut now_score = evaluator.get_score(&now_state);
+    let mut best_score = now_score;
+    let mut best_state = now_state.clone();
+    let mut acceptor = Acceptor::new(config, now_score);
+
+    let mut loop_cnt = 0;
+    while let Some(progress) = progress(loop_cnt) {
+        loop_cnt += 1;
+        let (operator, next_state) = generator.generate(&now_state, rng);
+        let next_score = evaluator.get_score(&next_state);
+        let temp = config.temperature(progress);
+        let is_accepted = acceptor.accept(now_score, next_score, best_score, temp, rng);
+        stats.record(operator, is_accepted);
+        if is_accepted {
+            now_score = next_score;
+            now_state = next_state;
+            if config.objective.gain(best_score, now_score) > 0 {
+                best_score = now_score;
+                best_state = now_state.clone();
+            }
+        }
+    }
+    (best_state, stats)
+}
+
+// 初期配置から独立にruns回searchを行い、objectiveで最も良い配置と、操作ごとの採用数の合計を返す
+// thread_number本のスレッドで分担する。各回の予算(反復回数や時間)はsearchに持たせる
+// 乱数は各回にrngから分けて渡すので、反復回数で打ち切るならスレッド数によらず同じ結果になる
+pub fn multi_start<R, F>(
+    state: &MazeState,
+    runs: usize,
+    thread_number: usize,
+    objective: Objective,
+    rng: &mut R,
+    search: F,
+) -> (MazeState, OperatorStats)
+where
+    R: Rng,
+    F: Fn(&MazeState, &mut RandomGenerator) -> (MazeState, OperatorStats) + Sync,
+{
+    assert!(runs > 0);
+    let rngs: Vec<RandomGenerator> = (0..runs).map(|_| split_rng(rng)).collect();
+    let results = run_tasks(runs, thread_number, || (), |_, run| search(state, &mut rngs[run].clone()), |_, _| {});
+    let mut evaluator = IncrementalEvaluator::new(state);
+    let mut total_stats = OperatorStats::default();
+    let mut best: Option<(ScoreType, MazeState)> = None;
+    for (result, stats) in results {
+        total_stats.merge(&stats);
+        let score = evaluator.get_score(&result);
+        if best.as_ref().is_none_or(|(best_score, _)| objective.gain(*best_score, score) > 0) {
+            best = Some((score, result));
+        }
+    }
+    (best.unwrap().1, total_stats)
+}
+
+// 小さな盤面で、キャラクターの配置を全て調べてget_scoreが最大の配置を求める(分枝限定法)
+// キャラクターは同時に動いて区別できないので、同じマスに重ねる場合も含めて組み合わせだけを調べる
+// 各キャラクターが取れる点数の上界の和か、置いたマスを除いて全員で取れる点数の上界(joint_bound)が
+// 最良のスコア以下になる配置は調べない
+// 盤面の点数の大半を取れる配置が最適になりやすいので、点数の高いマスに置く配置ほどjoint_boundで除ける
+// ターン数が長いと上界が緩く、最悪でH*W+N-1個からN個選ぶ組み合わせを全て評価する
+// 最適な配置、そのスコア、評価した配置の数を返す
+pub fn exact_solve(state: &MazeState) -> (MazeState, ScoreType, usize) {
+    let evaluator = IncrementalEvaluator::new(state);
+    let bounds = walk_bounds(&evaluator);
+    // 上界の大きいマスから順に置くので、残りのキャラクターの上界は次に置くマスの上界で抑えられる
+    let mut order: Vec<usize> = (0..bounds.len()).collect();
+    order.sort_by_key(|&cell| std::cmp::Reverse(bounds[cell]));
+    // 取れるマスは高々キャラクターの数×ターン数なので、点数の高い順にその数だけ取った合計も上界になる
+    let mut points = evaluator.points.clone();
+    points.sort_unstable_by_key(|&point| std::cmp::Reverse(point));
+    let take = (state.characters.len() * evaluator.turn_number).min(points.len());
+    let mut colored_cells = [Vec::new(), Vec::new()];
+    for (cell, &point) in evaluator.points.iter().enumerate() {
+        colored_cells[(cell / state.w + cell % state.w) % 2].push((point, cell));
+    }
+    let colored_cells = colored_cells.map(|mut cells| {
+        cells.sort_unstable_by_key(|&(point, _)| std::cmp::Reverse(point));
+        cells.into_iter().map(|(_, cell)| cell).collect()
+    });
+    let mut solver = ExactSolver {
+        w: state.w,
+        order,
+        bounds,
+        total_bound: points[..take].iter().sum(),
+        colored_cells,
+        evaluator,
+        state: state.clone(),
+        best: None,
+        evaluated: 0,
+    };
+    solver.search(0, 0, 0);
+    let (score, best_state) = solver.best.unwrap();
+    (best_state, score, solver.evaluated)
+}
+
+// bounds[cell]はそのマスから残りのターン数だけ動いて取れる点数の上界
+// 他のキャラクターを無視し、隣のマスへの移動を繰り返して得る点数の最大値
+// 直前にいたマスは自分が取って0になっているので、すぐに戻る移動では点数を得ない
+fn walk_bounds(evaluator: &IncrementalEvaluator) -> Vec<ScoreType> {
+    let neighbors = &evaluator.neighbors;
+    let points = &evaluator.points;
+    // from_bounds[cell][action]は、行動actionの移動先から来てcellにいるときの上界
+    let mut from_bounds = vec![[0; 4]; neighbors.len()];
+    let mut bounds = vec![0; neighbors.len()];
+    for _ in 0..evaluator.turn_number {
+        let mut next_from_bounds = vec![[0; 4]; neighbors.len()];
+        for cell in 0..neighbors.len() {
+            // 隣のマスがない(1x1の盤面)ならその場に留まる
+            let mut moves: Vec<(usize, ScoreType)> = Vec::new();
+            for (action, &next) in neighbors[cell].iter().enumerate() {
+                if next != usize::MAX {
+                    let back = neighbors[next].iter().position(|&back| back == cell).unwrap();
+                    moves.push((action, points[next] + from_bounds[next][back]));
+                }
+            }
+            bounds[cell] = moves.iter().map(|&(_, bound)| bound).max().unwrap_or(points[cell] + bounds[cell]);
+            for (from, next_bound) in next_from_bounds[cell].iter_mut().enumerate() {
+                let back = neighbors[cell][from];
+                *next_bound = moves
+                    .iter()
+                    .map(|&(action, bound)| if action == from { bound - points[back] } else { bound })
+                    .max()
+                    .unwrap_or(bounds[cell]);
+            }
+        }
+        from_bounds = next_from_bounds;
+    }
+    bounds
+}
+
+struct ExactSolver {
+    w: usize,
+    order: Vec<usize>,
+    bounds: Vec<ScoreType>,
+    total_bound: ScoreType,
+    // colored_cells[color]は(y + x) % 2がcolorのマスを点数の高い順に並べたもの
+    colored_cells: [Vec<usize>; 2],
+    evaluator: IncrementalEvaluator,
+    // 作業用の状態。id番目より前のキャラクターは置いたマスにいる
+    state: MazeState,
+    best: Option<(ScoreType, MazeState)>,
+    evaluated: usize,
+}
+impl ExactSolver {
+    // id番目以降のキャラクターをorder[from..]のマスに置く。placed_boundは置いたキャラクターの上界の和
+    fn search(&mut self, id: usize, from: usize, placed_bound: ScoreType) {
+        let rest = self.state.characters.len() - id;
+        if rest == 0 {
+            let score = self.evaluator.get_score(&self.state);
+            self.evaluated += 1;
+            if self.best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+                self.best = Some((score, self.state.clone()));
+            }
+            return;
+        }
+        for index in from..self.order.len() {
+            let cell = self.order[index];
+            let bound = self.state.game_score
+                + (placed_bound + rest as ScoreType * self.bounds[cell]).min(self.total_bound);
+            // orderは上界の大きい順なので、ここで打ち切れば後のマスも調べなくてよい
+            if self.best.as_ref().is_some_and(|(best_score, _)| bound <= *best_score) {
+                break;
+            }
+            self.state.set_character(id, cell / self.w, cell % self.w);
+            if self.best.as_ref().is_some_and(|(best_score, _)| self.joint_bound(id + 1) <= *best_score) {
+                continue;
+            }
+            self.search(id + 1, index, placed_bound + self.bounds[cell]);
+        }
+    }
+
+    // 最初のplaced人を置いた状態で、全員が取れる点数の上界
+    // 最初にいたマスは点数を得ないまま0になるので誰も取れない
+    // マスを市松模様に塗ると1ターンごとに色が変わるので、1人がT回の移動で取れるのは
+    // 最初のマスと同じ色のマスが(T / 2)個、違う色のマスが(T + 1) / 2個まで。置いていないキャラクターはどちらも後者で抑える
+    fn joint_bound(&self, placed: usize) -> ScoreType {
+        let turn_number = self.evaluator.turn_number;
+        let starts = &self.state.characters[..placed];
+        let mut slots = [(self.state.characters.len() - placed) * turn_number.div_ceil(2); 2];
+        for start in starts.iter() {
+            let color = (start.y + start.x) % 2;
+            slots[color] += turn_number / 2;
+            slots[1 - color] += turn_number.div_ceil(2);
+        }
+        let mut bound = self.state.game_score;
+        for (cells, &slot) in self.colored_cells.iter().zip(slots.iter()) {
+            bound += cells
+                .iter()
+                .filter(|&&cell| !starts.contains(&Coord { y: cell / self.w, x: cell % self.w }))
+                .take(slot)
+                .map(|&cell| self.evaluator.points[cell])
+                .sum::<ScoreType>();
+        }
+        bound
+    }
+}
+
+// aiで配置を決めた1ゲームのスコアを返す
+// AIの乱数も盤面と同じseedから作るので、seedを指定すれば同じゲームを再現できる
+pub fn playGame(ai: &Ch4AIFunction, seed: Option<u64>) -> ScoreType {
+    play_state(ai, &MazeState::new(seed), &mut new_rng(seed))
+}
+
+// stateに対してaiで配置を決めたときのスコアを返す
+pub fn play_state(ai: &Ch4AIFunction, state: &MazeState, rng: &mut RandomGenerator) -> ScoreType {
+    ai(state, rng).get_score(false)
+}
+
+// ゲームをgame_number回プレイしてスコアの平均と標準偏差を返す
+// thread_number本のスレッドで並列にプレイしても、結果はseedの順に表示して集計する
+// 局所探索のAIなら、ゲームごとに近傍の操作ごとの採用率も表示する
+pub fn test_AI_score(new_ai: &Ch4AIFactory, game_number: usize, seed: Option<u64>, thread_number: usize) -> Summary {
+    let results = run_tasks(
+        game_number,
+        thread_number,
+        new_ai,
+        |ai, cnt| {
+            take_operator_stats();
+            let score = playGame(&**ai, game_seed(seed, cnt)) as f64;
+            (score, take_operator_stats())
+        },
+        |cnt, (score, stats)| {
+            report_operator_stats(stats);
+            eprintln!("game: {} end, score:{}", cnt, score);
+        },
+    );
+    let scores: Vec<f64> = results.iter().map(|&(score, _)| score).collect();
+    Summary::new(&scores)
+}
+
+// 操作を1回も試していなければ(局所探索でないAIなら)何も表示しない
+pub fn report_operator_stats(stats: &OperatorStats) {
+    if *stats != OperatorStats::default() {
+        eprintln!("acceptance rates: {}", stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // IncrementalEvaluatorとget_scoreのスコアが一致することを、transitionで1人ずつ動かした配置の列で確かめる
+    fn assert_evaluator_matches(state: &MazeState, number: usize, rng: &mut RandomGenerator) {
+        let mut evaluator = IncrementalEvaluator::new(state);
+        let mut now_state = random_action(state, rng);
+        for _ in 0..number {
+            assert_eq!(evaluator.get_score(&now_state), now_state.get_score(false), "{:?}", now_state.characters);
+            now_state.transition(rng);
+        }
+    }
+
+    // 全ての配置をget_scoreで調べたときの最大のスコア
+    fn brute_force_score(state: &MazeState) -> ScoreType {
+        let (cell_number, n) = (state.h * state.w, state.characters.len());
+        (0..cell_number.pow(n as u32))
+            .map(|mut code| {
+                let mut now_state = state.clone();
+                for id in 0..n {
+                    let cell = code % cell_number;
+                    code /= cell_number;
+                    now_state.set_character(id, cell / state.w, cell % state.w);
+                }
+                now_state.get_score(false)
+            })
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn exact_solve_matches_brute_force() {
+        // 1x1の盤面はmove_playerが盤面の外に出るので、check_movesと同じく除く
+        let sizes = [(1, 2), (1, 4), (2, 2), (2, 3), (3, 3), (3, 4)];
+        for (h, w) in sizes {
+            for end_turn in [0, 1, 2, 5, 8] {
+                for character_number in 1..=3 {
+                    let seed = (h * 1000 + w * 100 + end_turn * 10 + character_number) as u64;
+                    let state = MazeState::with_size(Some(seed), h, w, end_turn, character_number);
+                    let (best_state, score, evaluated) = exact_solve(&state);
+                    let case = format!("{}x{} {} turns {} characters", h, w, end_turn, character_number);
+                    assert_eq!(score, brute_force_score(&state), "{}", case);
+                    assert_eq!(best_state.get_score(false), score);
+                    assert!(evaluated >= 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_solve_matches_brute_force_in_the_middle_of_a_game() {
+        for seed in 0..10 {
+            let rng = &mut new_rng(Some(seed));
+            let mut state = random_action(&MazeState::with_size(Some(seed), 3, 3, 7, 2), rng);
+            for _ in 0..3 {
+                state.advance();
+            }
+            let (best_state, score, _) = exact_solve(&state);
+            assert_eq!(score, brute_force_score(&state));
+            assert_eq!(best_state.get_score(false), score);
+        }
+    }
+
+    #[test]
+    fn neighborhood_parse_reads_weights_and_rejects_swap() {
+        let neighborhood = Neighborhood::parse("teleport:1, hotspot:2", 3).unwrap();
+        assert_eq!(neighborhood, Neighborhood { weights: [1.0, 0.0, 2.0], radius: 3 });
+        assert_eq!(
+            Neighborhood::parse("teleport:1,swap:1", 2).unwrap_err(),
+            "swap is not supported: exchanging two characters never changes the score"
+        );
+        assert_eq!(Neighborhood::parse("jump:1", 2).unwrap_err(), "unknown operator: jump");
+    }
+
+    #[test]
+    fn incremental_evaluator_matches_get_score() {
+        for seed in 0..5 {
+            let rng = &mut new_rng(Some(seed));
+            assert_evaluator_matches(&MazeState::new(Some(seed)), 2000, rng);
+        }
+    }
+
+    #[test]
+    fn incremental_evaluator_matches_get_score_on_small_boards() {
+        // 1x1の盤面はmove_playerが盤面の外に出るので、check_movesと同じく除く
+        let sizes = [(1, 2), (2, 1), (1, 5), (2, 2), (3, 2), (3, 3), (4, 5)];
+        for (h, w) in sizes {
+            for end_turn in [0, 1, 3, 8] {
+                for character_number in 1..=4 {
+                    let seed = (h * 1000 + w * 100 + end_turn * 10 + character_number) as u64;
+                    let state = MazeState::with_size(Some(seed), h, w, end_turn, character_number);
+                    assert_evaluator_matches(&state, 200, &mut new_rng(Some(seed)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_evaluator_matches_get_score_on_shared_start_cells() {
+        let rng = &mut new_rng(Some(0));
+        for seed in 0..20 {
+            let state = MazeState::with_size(Some(seed), 4, 4, 10, 4);
+            let mut evaluator = IncrementalEvaluator::new(&state);
+            for _ in 0..50 {
+                // 全員か一部を同じマスに重ねる
+                let mut now_state = state.clone();
+                let (y, x) = (rng.gen_range(0, state.h), rng.gen_range(0, state.w));
+                for id in 0..state.characters.len() {
+                    if id == 0 || rng.gen_bool(0.7) {
+                        now_state.set_character(id, y, x);
+                    } else {
+                        now_state.set_character(id, rng.gen_range(0, state.h), rng.gen_range(0, state.w));
+                    }
+                }
+                assert_eq!(evaluator.get_score(&now_state), now_state.get_score(false), "{:?}", now_state.characters);
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_evaluator_continues_from_the_current_turn() {
+        for seed in 0..10 {
+            let rng = &mut new_rng(Some(seed));
+            let mut state = random_action(&MazeState::with_size(Some(seed), 6, 6, 12, 3), rng);
+            for _ in 0..4 {
+                state.advance();
+            }
+            assert_evaluator_matches(&state, 200, rng);
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64, what: &str) {
+        assert!((actual - expected).abs() < 1e-9, "{}: {} != {}", what, actual, expected);
+    }
+
+    #[test]
+    fn schedules_go_from_start_to_end_temperature() {
+        let middle_log = 100.0 - 99.0 * 50.5f64.ln() / 100f64.ln();
+        let cases = [
+            (Schedule::LINEAR, [(0.0, 100.0), (0.5, 50.5), (1.0, 1.0)].to_vec()),
+            (Schedule::EXPONENTIAL, [(0.0, 100.0), (0.5, 10.0), (1.0, 1.0)].to_vec()),
+            (Schedule::LOGARITHMIC, [(0.0, 100.0), (0.5, middle_log), (1.0, 1.0)].to_vec()),
+            // 周期ごとにstart_tempに戻り、最後はend_tempで終わる
+            (
+                Schedule::REHEATING(2),
+                [(0.0, 100.0), (0.25, 50.5), (0.5, 100.0), (0.75, 50.5), (1.0, 1.0)].to_vec(),
+            ),
+        ];
+        for (schedule, temperatures) in cases {
+            let config = AnnealingConfig { start_temp: 100.0, end_temp: 1.0, schedule, ..Default::default() };
+            for (progress, expected) in temperatures {
+                assert_close(config.temperature(progress), expected, &format!("{:?} at {}", schedule, progress));
+            }
+        }
+        // 対数の温度は線形より速く下がる
+        assert!(middle_log < 50.5);
+    }
+
+    #[test]
+    fn objective_gain_is_positive_for_improvements() {
+        assert_eq!(Objective::MAXIMIZE.gain(3, 5), 2);
+        assert_eq!(Objective::MAXIMIZE.gain(5, 3), -2);
+        assert_eq!(Objective::MINIMIZE.gain(3, 5), -2);
+        assert_eq!(Objective::MINIMIZE.gain(5, 3), 2);
+        assert_eq!(Objective::MAXIMIZE.gain(4, 4), 0);
+        assert_eq!(Objective::MINIMIZE.gain(4, 4), 0);
+    }
+
+    // 最大化の場面のスコアを、最小化では符号を反転して同じ判定になることも確かめる
+    fn acceptor(acceptance: Acceptance, objective: Objective, initial_score: ScoreType) -> (Acceptor, ScoreType) {
+        let sign = if objective == Objective::MAXIMIZE { 1 } else { -1 };
+        let config = AnnealingConfig { acceptance, objective, ..Default::default() };
+        (Acceptor::new(&config, sign * initial_score), sign)
+    }
+
+    #[test]
+    fn acceptance_rules_judge_improving_equal_and_worse_moves() {
+        let mut rng = new_rng(Some(0));
+        for objective in [Objective::MAXIMIZE, Objective::MINIMIZE] {
+            // 良くなる近傍と変わらない近傍は、温度0でもどの規則でも受け入れる
+            // (deluge以外。delugeは最も良いスコアとの比較なので下で調べる)
+            for acceptance in [Acceptance::METROPOLIS, Acceptance::THRESHOLD, Acceptance::LATE(3)] {
+                let (mut acceptor, sign) = acceptor(acceptance, objective, 10);
+                assert!(acceptor.accept(sign * 10, sign * 15, sign * 20, 0.0, &mut rng), "{:?}", acceptance);
+                assert!(acceptor.accept(sign * 15, sign * 15, sign * 20, 0.0, &mut rng), "{:?}", acceptance);
+            }
+
+            // metropolis: 幅5の悪化を温度0では拒み、温度5ではexp(-1)の確率で受け入れる
+            let (mut metropolis, sign) = acceptor(Acceptance::METROPOLIS, objective, 10);
+            assert!(!metropolis.accept(sign * 10, sign * 5, sign * 10, 0.0, &mut rng));
+            let accepted =
+                (0..10000).filter(|_| metropolis.accept(sign * 10, sign * 5, sign * 10, 5.0, &mut rng)).count();
+            assert!((accepted as f64 / 10000.0 - (-1f64).exp()).abs() < 0.02, "{:?} {}", objective, accepted);
+
+            // threshold: 温度以下の悪化だけ受け入れる
+            let (mut threshold, sign) = acceptor(Acceptance::THRESHOLD, objective, 10);
+            assert!(threshold.accept(sign * 10, sign * 5, sign * 10, 5.0, &mut rng));
+            assert!(!threshold.accept(sign * 10, sign * 5, sign * 10, 4.9, &mut rng));
+
+            // deluge: 最も良いスコアから温度を引いた水位と比べるので、今より良くても水位より下なら拒む
+            let (mut deluge, sign) = acceptor(Acceptance::DELUGE, objective, 10);
+            assert!(deluge.accept(sign * 20, sign * 15, sign * 20, 5.0, &mut rng));
+            assert!(!deluge.accept(sign * 20, sign * 15, sign * 20, 4.0, &mut rng));
+            assert!(!deluge.accept(sign * 10, sign * 12, sign * 20, 5.0, &mut rng));
+            assert!(deluge.accept(sign * 20, sign * 20, sign * 20, 0.0, &mut rng));
+
+            // late:2: 今より悪くても、2回前の今のスコア以上なら受け入れる
+            let (mut late, sign) = acceptor(Acceptance::LATE(2), objective, 5);
+            let steps = [(10, 7, true), (7, 4, false), (7, 6, false), (7, 7, true), (7, 6, false), (7, 8, true)];
+            for (step, (now_score, next_score, expected)) in steps.into_iter().enumerate() {
+                let is_accepted = late.accept(sign * now_score, sign * next_score, sign * 10, 100.0, &mut rng);
+                assert_eq!(is_accepted, expected, "{:?} step {}", objective, step);
+            }
+        }
+    }
+}
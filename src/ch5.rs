@@ -0,0 +1,390 @@
+// chapter5: 2人が交互に行動して盤面の数字を取り合う迷路ゲーム
+
+use rand::Rng;
+use std::sync::Arc;
+
+use crate::game_record::GameRecord;
+use crate::maze_text::MazeText;
+use crate::statistics::{MatchResult, Sprt};
+use crate::zobrist::ZobristTable;
+use crate::{
+    battle, dx, dy, new_rng, AIPairFactory, AlternateGameState, Coord, GameState, HashedGameState, RandomGenerator,
+    ScoreType, StringAIPair,
+};
+
+pub const H: usize = 5;
+pub const W: usize = 5;
+pub const END_TURN: usize = 10;
+
+#[derive(Clone, Copy, Debug, Eq)]
+pub struct Character {
+    pub y: usize,
+    pub x: usize,
+    pub game_score: ScoreType,
+}
+impl std::cmp::PartialEq for Character {
+    fn eq(&self, other: &Self) -> bool {
+        self.game_score == other.game_score
+    }
+}
+impl std::cmp::PartialOrd for Character {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl std::cmp::Ord for Character {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.game_score.cmp(&other.game_score)
+    }
+}
+impl Character {
+    pub fn new() -> Self {
+        Character { y: 0, x: 0, game_score: 0 }
+    }
+}
+impl Default for Character {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WinningStates {
+    WIN,
+    LOSE,
+    DRAW,
+    CONTINUE,
+}
+
+// characters[0]が常に現在の手番のプレイヤー
+// hashは盤面の点数、先手後手それぞれの位置、手番、ターン数から計算し、advanceで差分更新する
+#[derive(Clone, Debug)]
+pub struct MazeState {
+    pub h: usize,
+    pub w: usize,
+    pub end_turn: usize,
+    pub grid: Vec<Vec<usize>>,
+    pub turn: usize,
+    pub characters: Vec<Character>,
+    pub zobrist: Arc<ZobristTable>,
+    pub hash: u64,
+}
+impl MazeState {
+    pub fn new(seed: Option<u64>) -> Self {
+        Self::with_size(seed, H, W, END_TURN)
+    }
+
+    // 盤面の大きさと終了ターンを指定して生成する
+    pub fn with_size(seed: Option<u64>, h: usize, w: usize, end_turn: usize) -> Self {
+        let mut rng: rand::rngs::StdRng = match seed {
+            Some(seed) => rand::SeedableRng::seed_from_u64(seed),
+            None => rand::SeedableRng::from_entropy(),
+        };
+        let mut grid: Vec<Vec<usize>> = vec![vec![0; w]; h];
+        for cell in grid.iter_mut().flatten() {
+            *cell = rng.gen_range(0, 10);
+        }
+        let positions = [Coord { y: h / 2, x: 0 }, Coord { y: h / 2, x: w - 1 }];
+        Self::from_grid(grid, end_turn, positions)
+    }
+
+    // 盤面の点数と先手、後手の初期位置を指定して生成する。キャラクターのいるマスの点数は0にする
+    pub fn from_grid(mut grid: Vec<Vec<usize>>, end_turn: usize, positions: [Coord; 2]) -> Self {
+        let (h, w) = (grid.len(), grid[0].len());
+        let characters: Vec<Character> = positions
+            .iter()
+            .map(|position| Character { y: position.y, x: position.x, game_score: 0 })
+            .collect();
+        for character in characters.iter() {
+            grid[character.y][character.x] = 0;
+        }
+        let mut state = MazeState {
+            h,
+            w,
+            end_turn,
+            grid,
+            turn: 0,
+            characters,
+            zobrist: Arc::new(ZobristTable::new(h, w, 9, 2, end_turn)),
+            hash: 0,
+        };
+        state.hash = state.compute_hash();
+        state
+    }
+
+    // 1人目のキャラクターを先手として読み込む
+    pub fn from_maze_text(maze: &MazeText) -> Result<Self, String> {
+        maze.expect_characters(2)?;
+        maze.expect_moves()?;
+        Ok(Self::from_grid(maze.grid.clone(), maze.end_turn, [maze.characters[0], maze.characters[1]]))
+    }
+
+    // 現在の手番のプレイヤーを1人目として書き出す
+    pub fn to_maze_text(&self) -> MazeText {
+        MazeText {
+            height: self.h,
+            width: self.w,
+            end_turn: self.end_turn - self.turn,
+            characters: self.characters.iter().map(|character| Coord { y: character.y, x: character.x }).collect(),
+            grid: self.grid.clone(),
+        }
+    }
+
+    // characters[index]が先手なら0、後手なら1
+    fn player_id(&self, index: usize) -> usize {
+        (self.turn + index) % 2
+    }
+
+    // 差分更新を使わずに盤面全体からハッシュを計算する
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = self.zobrist.grid_hash(&self.grid) ^ self.zobrist.turn(self.turn);
+        for (index, character) in self.characters.iter().enumerate() {
+            hash ^= self.zobrist.character(self.player_id(index), character.y, character.x);
+        }
+        if !self.is_first() {
+            hash ^= self.zobrist.side();
+        }
+        hash
+    }
+
+    pub fn isIn(&self, x: isize, y: isize) -> bool {
+        x >= 0 && x < self.w as isize && y >= 0 && y < self.h as isize
+    }
+
+    pub fn get_winning_status(&self) -> WinningStates {
+        if self.is_done() {
+            if self.characters[0].game_score > self.characters[1].game_score {
+                return WinningStates::WIN;
+            } else if self.characters[0].game_score < self.characters[1].game_score {
+                return WinningStates::LOSE;
+            } else {
+                return WinningStates::DRAW;
+            }
+        }
+        WinningStates::CONTINUE
+    }
+
+    pub fn get_score(&self) -> ScoreType {
+        self.characters[0].game_score - self.characters[1].game_score
+    }
+
+    // 先手、後手の順に並べた得点
+    pub fn first_player_scores(&self) -> [ScoreType; 2] {
+        if self.is_first() {
+            [self.characters[0].game_score, self.characters[1].game_score]
+        } else {
+            [self.characters[1].game_score, self.characters[0].game_score]
+        }
+    }
+
+    pub fn to_string(&self) {
+        let [score_a, score_b] = self.first_player_scores();
+        eprintln!("turn:{}, A:{}, B:{}", self.turn, score_a, score_b);
+        let mut str: Vec<Vec<char>> = self
+            .grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&cell| if cell == 0 { '.' } else { std::char::from_digit(cell as u32, 10).unwrap() })
+                    .collect()
+            })
+            .collect();
+        if self.is_first() {
+            str[self.characters[0].y][self.characters[0].x] = 'A';
+            str[self.characters[1].y][self.characters[1].x] = 'B';
+        } else {
+            str[self.characters[0].y][self.characters[0].x] = 'B';
+            str[self.characters[1].y][self.characters[1].x] = 'A';
+        }
+
+        for row in str.iter() {
+            eprintln!("{} ", row.iter().collect::<String>());
+        }
+        eprintln!();
+    }
+}
+impl GameState for MazeState {
+    fn legal_actions(&self) -> Vec<usize> {
+        let mut actions: Vec<usize> = Vec::new();
+        let character: &Character = &self.characters[0];
+        for action in 0..4 {
+            let x: isize = character.x as isize + dx[action];
+            let y: isize = character.y as isize + dy[action];
+            if self.isIn(x, y) {
+                actions.push(action);
+            }
+        }
+        actions
+    }
+
+    fn advance(&mut self, action: usize) {
+        assert!(action < 4);
+        let player_id = self.player_id(0);
+        let character: &mut Character = &mut self.characters[0];
+        let next_y = (character.y as isize + dy[action]) as usize;
+        let next_x = (character.x as isize + dx[action]) as usize;
+        self.hash ^= self.zobrist.character(player_id, character.y, character.x);
+        self.hash ^= self.zobrist.character(player_id, next_y, next_x);
+        character.y = next_y;
+        character.x = next_x;
+        let point = self.grid[next_y][next_x];
+        if point > 0 {
+            character.game_score += point as ScoreType;
+            self.grid[next_y][next_x] = 0;
+            self.hash ^= self.zobrist.cell(next_y, next_x, point);
+        }
+        self.hash ^= self.zobrist.turn(self.turn) ^ self.zobrist.turn(self.turn + 1) ^ self.zobrist.side();
+        self.turn += 1;
+        self.characters.swap(0, 1);
+    }
+
+    fn is_done(&self) -> bool {
+        assert!(self.turn <= self.end_turn);
+        self.turn == self.end_turn
+    }
+
+    fn evaluate(&self) -> ScoreType {
+        self.get_score()
+    }
+}
+
+impl HashedGameState for MazeState {
+    fn zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+}
+impl AlternateGameState for MazeState {
+    fn is_first(&self) -> bool {
+        self.turn.is_multiple_of(2)
+    }
+
+    fn get_first_player_score_fow_win_rate(&self) -> f64 {
+        match self.get_winning_status() {
+            WinningStates::WIN => {
+                if self.is_first() {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            WinningStates::LOSE => {
+                if self.is_first() {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            _ => 0.5,
+        }
+    }
+
+    // 両者の得点の合計に対する現在の手番のプレイヤーの得点の割合
+    fn get_score_rate(&self) -> f64 {
+        let total_score = self.characters[0].game_score + self.characters[1].game_score;
+        if total_score == 0 {
+            return 0.5;
+        }
+        self.characters[0].game_score as f64 / total_score as f64
+    }
+}
+
+// ランダムに途中まで進めた状態をgame_number個作る
+// 同じ番号には毎回同じ状態を作る
+pub fn get_sample_states(game_number: usize) -> Vec<MazeState> {
+    let mut states: Vec<MazeState> = Vec::new();
+    for i in 0..game_number {
+        let mut rng = new_rng(Some(i as u64));
+        let mut state = MazeState::new(Some(i as u64));
+        let turn = rng.gen_range(0, END_TURN);
+        for _ in 0..turn {
+            state.advance(crate::search::random_action(&state, &mut rng));
+        }
+        states.push(state);
+    }
+    states
+}
+
+// ais[0]を1p、ais[1]を2pとして1ゲームプレイし、盤面を表示しながら進める
+// 棋譜を返す。棋譜のseedはNoneなので、必要なら呼び出し側で設定する
+pub fn playGame(ais: &[StringAIPair<MazeState>], mut state: MazeState, rng: &mut RandomGenerator) -> GameRecord {
+    let mut record = GameRecord::new([ais[0].0.clone(), ais[1].0.clone()], None, state.clone());
+    eprintln!("initial state");
+    state.to_string();
+    let mut player = 0;
+    while !state.is_done() {
+        eprintln!("{}p-----------------------------------", player + 1);
+        let action = ais[player].1(&state, rng);
+        record.actions.push(action);
+        state.advance(action); //ここで手番が相手に移る
+        state.to_string();
+        player ^= 1;
+    }
+    let first_player_win_rate = state.get_first_player_score_fow_win_rate();
+    if first_player_win_rate == 1.0 {
+        eprintln!("1p win");
+    } else if first_player_win_rate == 0.0 {
+        eprintln!("2p win");
+    } else {
+        eprintln!("draw");
+    }
+    record.scores = Some(state.first_player_scores());
+    record
+}
+
+// new_state(i)から始まるゲームをgame_number×2(先手後手を交代)回プレイしてaisの0番目のAIの勝率を表示する。
+// record_dirを指定した場合は、全てのゲームの棋譜をそのディレクトリにgame{i}-{j}.txtとして保存する
+// jは0ならaisの0番目、1なら1番目のAIが先手で、棋譜のseedは盤面の番号i
+pub fn test_first_player_win_rate(
+    new_ais: &AIPairFactory<MazeState>,
+    game_number: usize,
+    new_state: impl Fn(usize) -> MazeState + Sync,
+    seed: Option<u64>,
+    thread_number: usize,
+    sprt: Option<Sprt>,
+    record_dir: Option<&str>,
+) -> Result<MatchResult, String> {
+    if let Some(dir) = record_dir {
+        std::fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir, e))?;
+    }
+    let mut error = None;
+    let result = battle::test_first_player_win_rate(new_ais, game_number, new_state, seed, thread_number, sprt, |game| {
+        let (Some(dir), None) = (record_dir, &error) else {
+            return;
+        };
+        let players = [game.players[0].to_string(), game.players[1].to_string()];
+        let mut record = GameRecord::new(players, Some(game.index as u64), game.initial_state.clone());
+        record.actions = game.actions.to_vec();
+        let path = std::path::Path::new(dir).join(format!("game{}-{}.txt", game.index, game.first));
+        error = record
+            .replay(|_, _| {})
+            .and_then(|state| {
+                record.scores = Some(state.first_player_scores());
+                record.save(&path.to_string_lossy())
+            })
+            .err();
+    });
+    match error {
+        Some(error) => Err(error),
+        None => Ok(result),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::random_action;
+
+    // advanceで差分更新したハッシュが、盤面全体から計算し直したハッシュと一致する
+    #[test]
+    fn incremental_hash_matches_compute_hash_along_random_games() {
+        let mut rng = new_rng(Some(0));
+        for seed in 0..100 {
+            let mut state = MazeState::new(Some(seed));
+            assert_eq!(state.zobrist_hash(), state.compute_hash(), "seed {} turn 0", seed);
+            while !state.is_done() {
+                state.advance(random_action(&state, &mut rng));
+                assert_eq!(state.zobrist_hash(), state.compute_hash(), "seed {} turn {}", seed, state.turn);
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+// chapter6: 2人が同時に行動して盤面の数字を取り合う迷路ゲーム
+// 2人が同じマスに移動した場合は、そのマスの点数を両方が得る
+
+use rand::Rng;
+use std::rc::Rc;
+
+use crate::battle::MatchPlayer;
+use crate::statistics::{MatchResult, Sprt};
+use crate::{battle, dx, dy, RandomGenerator, ScoreType, SimultaneousGameState};
+
+pub const H: usize = 5;
+pub const W: usize = 5;
+pub const END_TURN: usize = 20;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Character {
+    pub y: usize,
+    pub x: usize,
+    pub game_score: ScoreType,
+}
+
+// 先手から見た勝敗
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WinningStates {
+    WIN,
+    LOSE,
+    DRAW,
+    CONTINUE,
+}
+
+// characters[0]が先手、characters[1]が後手で、入れ替わることはない
+#[derive(Clone, Debug)]
+pub struct MazeState {
+    pub grid: Vec<Vec<usize>>,
+    pub turn: usize,
+    pub characters: Vec<Character>,
+}
+impl MazeState {
+    // 公平になるよう盤面は左右対称に生成する
+    pub fn new(seed: Option<u64>) -> Self {
+        let mut rng: rand::rngs::StdRng = match seed {
+            Some(seed) => rand::SeedableRng::seed_from_u64(seed),
+            None => rand::SeedableRng::from_entropy(),
+        };
+        let characters = vec![
+            Character { y: H / 2, x: W / 2 - 1, game_score: 0 },
+            Character { y: H / 2, x: W / 2 + 1, game_score: 0 },
+        ];
+        let mut grid: Vec<Vec<usize>> = vec![vec![0; W]; H];
+        for row in grid.iter_mut() {
+            for x in 0..W.div_ceil(2) {
+                let point = rng.gen_range(0, 10);
+                row[x] = point;
+                row[W - 1 - x] = point;
+            }
+        }
+        for character in characters.iter() {
+            grid[character.y][character.x] = 0;
+        }
+        MazeState {
+            grid,
+            turn: 0,
+            characters,
+        }
+    }
+
+    pub fn isIn(x: isize, y: isize) -> bool {
+        x >= 0 && x < W as isize && y >= 0 && y < H as isize
+    }
+
+    pub fn get_winning_status(&self) -> WinningStates {
+        if !self.is_done() {
+            return WinningStates::CONTINUE;
+        }
+        match self.characters[0].game_score.cmp(&self.characters[1].game_score) {
+            std::cmp::Ordering::Greater => WinningStates::WIN,
+            std::cmp::Ordering::Less => WinningStates::LOSE,
+            std::cmp::Ordering::Equal => WinningStates::DRAW,
+        }
+    }
+
+    pub fn to_string(&self) {
+        eprintln!(
+            "turn:{}, A:{}, B:{}",
+            self.turn, self.characters[0].game_score, self.characters[1].game_score
+        );
+        let mut str: Vec<Vec<char>> = self
+            .grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&cell| if cell == 0 { '.' } else { std::char::from_digit(cell as u32, 10).unwrap() })
+                    .collect()
+            })
+            .collect();
+        let [a, b] = [self.characters[0], self.characters[1]];
+        if a.y == b.y && a.x == b.x {
+            str[a.y][a.x] = 'X';
+        } else {
+            str[a.y][a.x] = 'A';
+            str[b.y][b.x] = 'B';
+        }
+        for row in str.iter() {
+            eprintln!("{}", row.iter().collect::<String>());
+        }
+        eprintln!();
+    }
+}
+impl SimultaneousGameState for MazeState {
+    fn legal_actions(&self, player_id: usize) -> Vec<usize> {
+        let character = &self.characters[player_id];
+        (0..4)
+            .filter(|&action| Self::isIn(character.x as isize + dx[action], character.y as isize + dy[action]))
+            .collect()
+    }
+
+    fn advance(&mut self, action0: usize, action1: usize) {
+        for (character, action) in self.characters.iter_mut().zip([action0, action1]) {
+            assert!(action < 4);
+            character.y = (character.y as isize + dy[action]) as usize;
+            character.x = (character.x as isize + dx[action]) as usize;
+            character.game_score += self.grid[character.y][character.x] as ScoreType;
+        }
+        for character in self.characters.iter() {
+            self.grid[character.y][character.x] = 0;
+        }
+        self.turn += 1;
+    }
+
+    fn is_done(&self) -> bool {
+        assert!(self.turn <= END_TURN);
+        self.turn == END_TURN
+    }
+
+    fn get_first_player_score_fow_win_rate(&self) -> f64 {
+        match self.get_winning_status() {
+            WinningStates::WIN => 1.0,
+            WinningStates::LOSE => 0.0,
+            _ => 0.5,
+        }
+    }
+}
+
+// 状態と自分のplayer_idを受け取って行動を返すAI
+pub type SimultaneousAIFunction = dyn Fn(&MazeState, usize, &mut RandomGenerator) -> usize;
+pub type StringSimultaneousAIPair = (String, Rc<SimultaneousAIFunction>);
+// 並列に対戦させるとき、スレッドごとに同じAIを作り直すための関数
+pub type SimultaneousAIPairFactory = dyn Fn() -> [StringSimultaneousAIPair; 2] + Sync;
+
+// ais[0]を1p、ais[1]を2pとして1ゲームプレイし、盤面を表示しながら進める
+pub fn playGame(ais: &[StringSimultaneousAIPair], mut state: MazeState, rng: &mut RandomGenerator) -> WinningStates {
+    eprintln!("initial state");
+    state.to_string();
+    while !state.is_done() {
+        let action0 = ais[0].1(&state, 0, rng);
+        let action1 = ais[1].1(&state, 1, rng);
+        eprintln!("actions {} {}", action0, action1);
+        state.advance(action0, action1);
+        state.to_string();
+    }
+    match state.get_winning_status() {
+        WinningStates::WIN => eprintln!("1p win"),
+        WinningStates::LOSE => eprintln!("2p win"),
+        _ => eprintln!("draw"),
+    }
+    state.get_winning_status()
+}
+
+impl MatchPlayer<MazeState> for Rc<SimultaneousAIFunction> {
+    // 両者の行動を、ターンごとに先手、後手の順に並べて返す
+    fn play_game(first: &Self, second: &Self, mut state: MazeState, rng: &mut RandomGenerator) -> (f64, Vec<usize>) {
+        let mut actions = Vec::new();
+        while !state.is_done() {
+            let action0 = first(&state, 0, rng);
+            let action1 = second(&state, 1, rng);
+            actions.extend([action0, action1]);
+            state.advance(action0, action1);
+        }
+        (state.get_first_player_score_fow_win_rate(), actions)
+    }
+}
+
+// MazeState::new(Some(i))から始まるゲームをgame_number×2(1pと2pを交代)回プレイしてaisの0番目のAIの成績を表示する。
+// 並列化や集計、SPRTでの打ち切りは2人交互ゲームと同じくbattle::test_first_player_win_rateで行う
+pub fn test_first_player_win_rate(
+    new_ais: &SimultaneousAIPairFactory,
+    game_number: usize,
+    seed: Option<u64>,
+    thread_number: usize,
+    sprt: Option<Sprt>,
+) -> MatchResult {
+    let new_state = |i: usize| MazeState::new(Some(i as u64));
+    battle::test_first_player_win_rate(new_ais, game_number, new_state, seed, thread_number, sprt, |_| {})
+}
@@ -0,0 +1,344 @@
+// chapter7: 壁のある迷路で盤面の数字を集める1人ゲーム
+// 壁は棒倒し法で生成するので、壁以外のマスはすべてつながっている
+
+use rand::Rng;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::bitboard::BitBoard;
+use crate::random::game_seed;
+use crate::search::take_dropped;
+use crate::statistics::Summary;
+use crate::worker_pool::run_tasks;
+use crate::zobrist::ZobristTable;
+use crate::{dx, dy, new_rng, AIFactory, AIFunction, Coord, GameState, HashedGameState, ScoreType};
+
+// 棒倒し法のため盤面の大きさは奇数にする
+pub const H: usize = 7;
+pub const W: usize = 7;
+pub const END_TURN: usize = 49;
+
+// 評価値の計算方法
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Evaluation {
+    // 得点のみ
+    NAIVE,
+    // 得点が同じなら最も近い点数のあるマスまでの距離が短いほうを高く評価する
+    DISTANCE,
+}
+
+// 最も近い点数のあるマスまでの距離を求めるときの盤面の表現
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Representation {
+    // gridとwallsの上で幅優先探索する
+    GRID,
+    // point_boardとwall_boardの上でビット演算で探索する
+    BITBOARD,
+}
+
+// wall_boardとpoint_boardはwallsとgridの点数のあるマスをビットボードで表したもの
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MazeState {
+    pub walls: Vec<Vec<bool>>,
+    pub grid: Vec<Vec<usize>>,
+    pub wall_board: BitBoard<H, W>,
+    pub point_board: BitBoard<H, W>,
+    pub turn: usize,
+    pub character: Coord,
+    pub game_score: ScoreType,
+    pub evaluation: Evaluation,
+    pub representation: Representation,
+    pub zobrist: Arc<ZobristTable>,
+    pub hash: u64,
+}
+impl MazeState {
+    pub fn new(seed: Option<u64>, evaluation: Evaluation, representation: Representation) -> Self {
+        let mut rng: rand::rngs::StdRng = match seed {
+            Some(seed) => rand::SeedableRng::seed_from_u64(seed),
+            None => rand::SeedableRng::from_entropy(),
+        };
+        let character = Coord {
+            y: rng.gen_range(0, H),
+            x: rng.gen_range(0, W),
+        };
+
+        // 奇数行奇数列に柱を立て、そこから1方向に棒を倒す。上に倒せるのは1行目の柱だけ
+        let mut walls = vec![vec![false; W]; H];
+        for y in (1..H).step_by(2) {
+            for x in (1..W).step_by(2) {
+                if y == character.y && x == character.x {
+                    continue;
+                }
+                walls[y][x] = true;
+                let direction_size = if y == 1 { 4 } else { 3 };
+                let direction = rng.gen_range(0, direction_size);
+                let ty = (y as isize + dy[direction]) as usize;
+                let tx = (x as isize + dx[direction]) as usize;
+                if ty == character.y && tx == character.x {
+                    continue;
+                }
+                walls[ty][tx] = true;
+            }
+        }
+
+        let mut grid = vec![vec![0; W]; H];
+        let mut wall_board = BitBoard::new();
+        let mut point_board = BitBoard::new();
+        for y in 0..H {
+            for x in 0..W {
+                if walls[y][x] {
+                    wall_board.set(y, x);
+                    continue;
+                }
+                if y == character.y && x == character.x {
+                    continue;
+                }
+                grid[y][x] = rng.gen_range(0, 10);
+                if grid[y][x] > 0 {
+                    point_board.set(y, x);
+                }
+            }
+        }
+
+        let mut state = MazeState {
+            walls,
+            grid,
+            wall_board,
+            point_board,
+            turn: 0,
+            character,
+            game_score: 0,
+            evaluation,
+            representation,
+            zobrist: Arc::new(ZobristTable::new(H, W, 9, 1, END_TURN)),
+            hash: 0,
+        };
+        state.hash = state.compute_hash();
+        state
+    }
+
+    pub fn compute_hash(&self) -> u64 {
+        self.zobrist.grid_hash(&self.grid) ^ self.zobrist.character(0, self.character.y, self.character.x)
+    }
+
+    pub fn isIn(x: isize, y: isize) -> bool {
+        x >= 0 && x < W as isize && y >= 0 && y < H as isize
+    }
+
+    // キャラクターから最も近い点数のあるマスまでの距離。なければH*W
+    pub fn get_distance_to_nearest_point(&self) -> usize {
+        match self.representation {
+            Representation::GRID => self.get_distance_to_nearest_point_by_grid(),
+            Representation::BITBOARD => self.get_distance_to_nearest_point_by_bitboard(),
+        }
+    }
+
+    fn get_distance_to_nearest_point_by_bitboard(&self) -> usize {
+        let mut start = BitBoard::new();
+        start.set(self.character.y, self.character.x);
+        BitBoard::distance_to_nearest(&start, &self.wall_board, &self.point_board).unwrap_or(H * W)
+    }
+
+    fn get_distance_to_nearest_point_by_grid(&self) -> usize {
+        let mut dist = vec![vec![usize::MAX; W]; H];
+        let mut queue = VecDeque::new();
+        dist[self.character.y][self.character.x] = 0;
+        queue.push_back(self.character);
+        while let Some(coord) = queue.pop_front() {
+            if self.grid[coord.y][coord.x] > 0 {
+                return dist[coord.y][coord.x];
+            }
+            for action in 0..4 {
+                let ty = coord.y as isize + dy[action];
+                let tx = coord.x as isize + dx[action];
+                if !Self::isIn(tx, ty) {
+                    continue;
+                }
+                let (ty, tx) = (ty as usize, tx as usize);
+                if self.walls[ty][tx] || dist[ty][tx] != usize::MAX {
+                    continue;
+                }
+                dist[ty][tx] = dist[coord.y][coord.x] + 1;
+                queue.push_back(Coord { y: ty, x: tx });
+            }
+        }
+        H * W
+    }
+
+    pub fn to_string(&self) {
+        eprintln!("turn:{}, score:{}", self.turn, self.game_score);
+        for y in 0..H {
+            let line: String = (0..W)
+                .map(|x| {
+                    if y == self.character.y && x == self.character.x {
+                        '@'
+                    } else if self.walls[y][x] {
+                        '#'
+                    } else if self.grid[y][x] > 0 {
+                        std::char::from_digit(self.grid[y][x] as u32, 10).unwrap()
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            eprintln!("{}", line);
+        }
+        eprintln!();
+    }
+}
+impl GameState for MazeState {
+    fn legal_actions(&self) -> Vec<usize> {
+        let mut actions: Vec<usize> = Vec::new();
+        for action in 0..4 {
+            let x: isize = self.character.x as isize + dx[action];
+            let y: isize = self.character.y as isize + dy[action];
+            if Self::isIn(x, y) && !self.walls[y as usize][x as usize] {
+                actions.push(action);
+            }
+        }
+        actions
+    }
+
+    fn advance(&mut self, action: usize) {
+        assert!(action < 4);
+        self.hash ^= self.zobrist.character(0, self.character.y, self.character.x);
+        self.character.x = (self.character.x as isize + dx[action]) as usize;
+        self.character.y = (self.character.y as isize + dy[action]) as usize;
+        assert!(!self.walls[self.character.y][self.character.x]);
+        let point = self.grid[self.character.y][self.character.x];
+        if point > 0 {
+            self.game_score += point as ScoreType;
+            self.grid[self.character.y][self.character.x] = 0;
+            self.point_board.del(self.character.y, self.character.x);
+            self.hash ^= self.zobrist.cell(self.character.y, self.character.x, point);
+        }
+        self.hash ^= self.zobrist.character(0, self.character.y, self.character.x);
+        self.turn += 1;
+    }
+
+    fn is_done(&self) -> bool {
+        assert!(self.turn <= END_TURN);
+        self.turn == END_TURN
+    }
+
+    fn evaluate(&self) -> ScoreType {
+        match self.evaluation {
+            Evaluation::NAIVE => self.game_score,
+            Evaluation::DISTANCE => {
+                self.game_score * (H * W) as ScoreType - self.get_distance_to_nearest_point() as ScoreType
+            }
+        }
+    }
+}
+impl HashedGameState for MazeState {
+    fn zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+// aiに従って1ゲームプレイしてスコアを返す
+pub fn play_game(
+    ai: &AIFunction<MazeState>,
+    seed: Option<u64>,
+    evaluation: Evaluation,
+    representation: Representation,
+) -> ScoreType {
+    let mut state = MazeState::new(seed, evaluation, representation);
+    let mut rng = new_rng(seed);
+    while !state.is_done() {
+        let action = ai(&state, &mut rng);
+        state.advance(action);
+    }
+    state.game_score
+}
+
+// ゲームをgame_number回プレイしてスコアの平均と標準偏差を返す
+// thread_number本のスレッドで並列にプレイしても、結果はseedの順に表示して集計する
+// 重複を除いた探索を使うAIなら、ゲームごとに捨てた状態の数も表示する
+pub fn test_AI_score(
+    new_ai: &AIFactory<MazeState>,
+    game_number: usize,
+    seed: Option<u64>,
+    evaluation: Evaluation,
+    representation: Representation,
+    thread_number: usize,
+) -> Summary {
+    let results = run_tasks(
+        game_number,
+        thread_number,
+        new_ai,
+        |ai, cnt| {
+            take_dropped();
+            let score = play_game(&*ai.1, game_seed(seed, cnt), evaluation, representation);
+            (score, take_dropped())
+        },
+        |cnt, &(score, dropped)| {
+            eprintln!("game: {} end, score:{}", cnt, score);
+            if dropped > 0 {
+                eprintln!("dropped duplicates:{}", dropped);
+            }
+        },
+    );
+    let scores: Vec<f64> = results.iter().map(|&(score, _)| score as f64).collect();
+    Summary::new(&scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 壁と点数を置き直した状態の、幅優先探索とビットボードそれぞれでの距離
+    fn distances(state: &MazeState) -> (usize, usize) {
+        (state.get_distance_to_nearest_point_by_grid(), state.get_distance_to_nearest_point_by_bitboard())
+    }
+
+    #[test]
+    fn bitboard_distance_matches_bfs_on_random_walls() {
+        let mut rng = new_rng(Some(0));
+        for _ in 0..1000 {
+            let mut state = MazeState::new(Some(rng.gen()), Evaluation::DISTANCE, Representation::GRID);
+            // 棒倒し法と違い、壁で区切られて点数のあるマスに届かない迷路も作る
+            let wall_rate = rng.gen_range(0.0, 0.7);
+            let point_rate = rng.gen_range(0.0, 0.3);
+            state.wall_board = BitBoard::new();
+            state.point_board = BitBoard::new();
+            for y in 0..H {
+                for x in 0..W {
+                    state.walls[y][x] = rng.gen_bool(wall_rate);
+                    let has_point = !state.walls[y][x] && rng.gen_bool(point_rate);
+                    state.grid[y][x] = if has_point { rng.gen_range(1, 10) } else { 0 };
+                    if state.walls[y][x] {
+                        state.wall_board.set(y, x);
+                    }
+                    if state.grid[y][x] > 0 {
+                        state.point_board.set(y, x);
+                    }
+                }
+            }
+            let (y, x) = (rng.gen_range(0, H), rng.gen_range(0, W));
+            state.walls[y][x] = false;
+            state.wall_board.del(y, x);
+            state.character = Coord { y, x };
+            let (by_grid, by_bitboard) = distances(&state);
+            assert_eq!(
+                by_grid, by_bitboard,
+                "walls {:?} grid {:?} character {:?}",
+                state.walls, state.grid, state.character
+            );
+        }
+    }
+
+    #[test]
+    fn bitboard_distance_matches_bfs_along_random_games() {
+        let mut rng = new_rng(Some(1));
+        for seed in 0..100 {
+            let mut state = MazeState::new(Some(seed), Evaluation::DISTANCE, Representation::GRID);
+            while !state.is_done() {
+                let (by_grid, by_bitboard) = distances(&state);
+                assert_eq!(by_grid, by_bitboard, "seed {} turn {}", seed, state.turn);
+                let legal_actions = state.legal_actions();
+                state.advance(legal_actions[rng.gen_range(0, legal_actions.len())]);
+            }
+        }
+    }
+}
@@ -0,0 +1,421 @@
+// chapter8: 重力付きの四目並べ(Connect Four)
+// 盤面を2次元配列で持つArrayStateと、ビットボードで持つBitboardStateの2通りで実装する
+
+use rand::Rng;
+use std::sync::Arc;
+
+use crate::statistics::{MatchResult, Sprt};
+use crate::zobrist::ZobristTable;
+use crate::{
+    battle, AIPairFactory, AlternateGameState, GameState, HashedGameState, RandomGenerator, ScoreType, StringAIPair,
+};
+
+pub const H: usize = 6;
+pub const W: usize = 7;
+
+// 勝敗が決まった局面の評価値。並びから計算する評価値より十分大きくする
+const WIN_SCORE: ScoreType = 10_000;
+
+// 盤面の表現によらない四目並べの状態の操作。並列に探索できるようスレッド間で共有できるものに限る
+// 行動は石を落とす列の番号で、yは下から数える
+pub trait ConnectFourState: AlternateGameState + HashedGameState + Send + Sync {
+    fn new() -> Self;
+    // (y, x)に置かれた石のプレイヤー(先手0、後手1)
+    fn stone(&self, y: usize, x: usize) -> Option<usize>;
+    // 四目並べたプレイヤー
+    fn winner(&self) -> Option<usize>;
+
+    fn to_string(&self) {
+        eprintln!("next:{}", if self.is_first() { 'x' } else { 'o' });
+        for y in (0..H).rev() {
+            let line: String = (0..W)
+                .map(|x| match self.stone(y, x) {
+                    Some(0) => 'x',
+                    Some(_) => 'o',
+                    None => '.',
+                })
+                .collect();
+            eprintln!("{}", line);
+        }
+        eprintln!();
+    }
+}
+
+// 現在の手番のプレイヤー(先手0、後手1)
+fn player_to_move<S: ConnectFourState>(state: &S) -> usize {
+    if state.is_first() {
+        0
+    } else {
+        1
+    }
+}
+
+// 4マスの並び全てについて、片方のプレイヤーの石しかない並びの石の数の2乗を足し引きする
+fn line_score<S: ConnectFourState>(state: &S) -> ScoreType {
+    let player = player_to_move(state);
+    let mut score = 0;
+    for (ly, lx) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+        for y in 0..H as isize {
+            for x in 0..W as isize {
+                let (end_y, end_x) = (y + ly * 3, x + lx * 3);
+                if end_y >= H as isize || end_x < 0 || end_x >= W as isize {
+                    continue;
+                }
+                let mut counts = [0; 2];
+                for i in 0..4 {
+                    if let Some(owner) = state.stone((y + ly * i) as usize, (x + lx * i) as usize) {
+                        counts[owner] += 1;
+                    }
+                }
+                if counts[1 - player] == 0 {
+                    score += counts[player] * counts[player];
+                } else if counts[player] == 0 {
+                    score -= counts[1 - player] * counts[1 - player];
+                }
+            }
+        }
+    }
+    score
+}
+
+// 勝敗が決まっていれば勝ち負けの評価値、そうでなければ並びから計算する評価値
+fn evaluate_state<S: ConnectFourState>(state: &S) -> ScoreType {
+    match state.winner() {
+        Some(winner) if winner == player_to_move(state) => WIN_SCORE,
+        Some(_) => -WIN_SCORE,
+        None if state.is_done() => 0,
+        None => line_score(state),
+    }
+}
+
+fn first_player_win_rate<S: ConnectFourState>(state: &S) -> f64 {
+    match state.winner() {
+        Some(0) => 1.0,
+        Some(_) => 0.0,
+        None => 0.5,
+    }
+}
+
+// 並びから計算する評価値をシグモイド関数で0.0から1.0に変換する
+fn score_rate<S: ConnectFourState>(state: &S) -> f64 {
+    if state.is_done() {
+        return state.get_win_rate_of_player_to_move();
+    }
+    1.0 / (1.0 + (-(line_score(state) as f64) / 10.0).exp())
+}
+
+fn new_zobrist_table() -> Arc<ZobristTable> {
+    Arc::new(ZobristTable::new(H, W, 0, 2, 0))
+}
+
+// board[y][x]に石を置いたプレイヤーを持つ
+// hashは置かれた石の位置とプレイヤーから計算し、advanceで差分更新する
+#[derive(Clone, Debug)]
+pub struct ArrayState {
+    pub board: [[Option<usize>; W]; H],
+    pub turn: usize,
+    pub winner: Option<usize>,
+    pub zobrist: Arc<ZobristTable>,
+    pub hash: u64,
+}
+impl ArrayState {
+    // (y, x)を含む並びにplayerの石が4つ以上続いているか
+    fn is_four_in_a_row(&self, y: usize, x: usize, player: usize) -> bool {
+        for (ly, lx) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+            let mut count = 1;
+            for sign in [1, -1] {
+                let mut ty = y as isize + ly * sign;
+                let mut tx = x as isize + lx * sign;
+                while ty >= 0
+                    && ty < H as isize
+                    && tx >= 0
+                    && tx < W as isize
+                    && self.board[ty as usize][tx as usize] == Some(player)
+                {
+                    count += 1;
+                    ty += ly * sign;
+                    tx += lx * sign;
+                }
+            }
+            if count >= 4 {
+                return true;
+            }
+        }
+        false
+    }
+}
+impl ConnectFourState for ArrayState {
+    fn new() -> Self {
+        ArrayState {
+            board: [[None; W]; H],
+            turn: 0,
+            winner: None,
+            zobrist: new_zobrist_table(),
+            hash: 0,
+        }
+    }
+
+    fn stone(&self, y: usize, x: usize) -> Option<usize> {
+        self.board[y][x]
+    }
+
+    fn winner(&self) -> Option<usize> {
+        self.winner
+    }
+}
+impl GameState for ArrayState {
+    fn legal_actions(&self) -> Vec<usize> {
+        (0..W).filter(|&x| self.board[H - 1][x].is_none()).collect()
+    }
+
+    fn advance(&mut self, action: usize) {
+        let player = player_to_move(self);
+        let y = (0..H).find(|&y| self.board[y][action].is_none()).unwrap();
+        self.board[y][action] = Some(player);
+        self.hash ^= self.zobrist.character(player, y, action);
+        if self.is_four_in_a_row(y, action, player) {
+            self.winner = Some(player);
+        }
+        self.turn += 1;
+    }
+
+    fn is_done(&self) -> bool {
+        self.winner.is_some() || self.turn == H * W
+    }
+
+    fn evaluate(&self) -> ScoreType {
+        evaluate_state(self)
+    }
+}
+impl HashedGameState for ArrayState {
+    fn zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+}
+impl AlternateGameState for ArrayState {
+    fn is_first(&self) -> bool {
+        self.turn.is_multiple_of(2)
+    }
+
+    fn get_first_player_score_fow_win_rate(&self) -> f64 {
+        first_player_win_rate(self)
+    }
+
+    fn get_score_rate(&self) -> f64 {
+        score_rate(self)
+    }
+}
+
+// 列xの下からy番目のマスをx*(H+1)+yビット目で表す。各列の一番上のビットは常に0の番兵で、
+// 横や斜めにシフトしたときに隣の列へ回り込まないようにしている
+// boards[i]はプレイヤーiの石の位置
+#[derive(Clone, Debug)]
+pub struct BitboardState {
+    pub boards: [u64; 2],
+    pub turn: usize,
+    pub winner: Option<usize>,
+    pub zobrist: Arc<ZobristTable>,
+    pub hash: u64,
+}
+impl BitboardState {
+    #[inline]
+    fn bit(y: usize, x: usize) -> u64 {
+        1 << (x * (H + 1) + y)
+    }
+
+    // 縦、横、2方向の斜めのそれぞれについて、シフトとANDで4つ続く石があるかを調べる
+    fn is_four_in_a_row(board: u64) -> bool {
+        [1, H + 1, H, H + 2].iter().any(|&shift| {
+            let pair = board & (board >> shift);
+            pair & (pair >> (2 * shift)) != 0
+        })
+    }
+}
+impl ConnectFourState for BitboardState {
+    fn new() -> Self {
+        BitboardState {
+            boards: [0; 2],
+            turn: 0,
+            winner: None,
+            zobrist: new_zobrist_table(),
+            hash: 0,
+        }
+    }
+
+    fn stone(&self, y: usize, x: usize) -> Option<usize> {
+        (0..2).find(|&player| self.boards[player] & Self::bit(y, x) != 0)
+    }
+
+    fn winner(&self) -> Option<usize> {
+        self.winner
+    }
+}
+impl GameState for BitboardState {
+    fn legal_actions(&self) -> Vec<usize> {
+        let all_board = self.boards[0] | self.boards[1];
+        (0..W).filter(|&x| all_board & Self::bit(H - 1, x) == 0).collect()
+    }
+
+    fn advance(&mut self, action: usize) {
+        let player = player_to_move(self);
+        let all_board = self.boards[0] | self.boards[1];
+        let column_mask = ((1 << H) - 1) << (action * (H + 1));
+        // 列の一番下に1を足すと、石が積まれている部分が繰り上がって次に置くマスのビットが立つ
+        let stone = (all_board + Self::bit(0, action)) & column_mask & !all_board;
+        assert!(stone != 0);
+        let y = stone.trailing_zeros() as usize - action * (H + 1);
+        self.boards[player] |= stone;
+        self.hash ^= self.zobrist.character(player, y, action);
+        if Self::is_four_in_a_row(self.boards[player]) {
+            self.winner = Some(player);
+        }
+        self.turn += 1;
+    }
+
+    fn is_done(&self) -> bool {
+        self.winner.is_some() || self.turn == H * W
+    }
+
+    fn evaluate(&self) -> ScoreType {
+        evaluate_state(self)
+    }
+}
+impl HashedGameState for BitboardState {
+    fn zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+}
+impl AlternateGameState for BitboardState {
+    fn is_first(&self) -> bool {
+        self.turn.is_multiple_of(2)
+    }
+
+    fn get_first_player_score_fow_win_rate(&self) -> f64 {
+        first_player_win_rate(self)
+    }
+
+    fn get_score_rate(&self) -> f64 {
+        score_rate(self)
+    }
+}
+
+// ランダムに途中まで進めた終局していない状態をgame_number個作る
+// 盤面の表現が違っても同じ番号には同じ状態を作る
+pub fn get_sample_states<S: ConnectFourState>(game_number: usize) -> Vec<S> {
+    (0..game_number)
+        .map(|i| {
+            let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(i as u64);
+            let turn = rng.gen_range(0, H * W);
+            random_state(turn, &mut rng)
+        })
+        .collect()
+}
+
+// 対戦の序盤にランダムに打つ手数
+pub const OPENING_MOVES: usize = 6;
+
+// 対戦のi番目のゲームの初期盤面。iから決まるOPENING_MOVES手をランダムに打っておき、
+// 決定的なAIどうしでもゲームごとに違う局面から始める。盤面の表現が違っても同じiには同じ盤面を作る
+pub fn opening_state<S: ConnectFourState>(index: usize) -> S {
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(index as u64);
+    random_state(OPENING_MOVES, &mut rng)
+}
+
+// 初期盤面からturn手までランダムに打った状態。終局する手の直前で止める
+fn random_state<S: ConnectFourState, R: Rng>(turn: usize, rng: &mut R) -> S {
+    let mut state = S::new();
+    for _ in 0..turn {
+        let legal_actions = state.legal_actions();
+        let mut next_state = state.clone();
+        next_state.advance(legal_actions[rng.gen_range(0, legal_actions.len())]);
+        if next_state.is_done() {
+            break;
+        }
+        state = next_state;
+    }
+    state
+}
+
+// ais[0]を1p(x)、ais[1]を2p(o)として1ゲームプレイし、盤面を表示しながら進める
+pub fn playGame<S: ConnectFourState>(ais: &[StringAIPair<S>], mut state: S, rng: &mut RandomGenerator) -> f64 {
+    eprintln!("initial state");
+    state.to_string();
+    let mut player = 0;
+    while !state.is_done() {
+        eprintln!("{}p-----------------------------------", player + 1);
+        let action = ais[player].1(&state, rng);
+        state.advance(action);
+        state.to_string();
+        player ^= 1;
+    }
+    let first_player_win_rate = state.get_first_player_score_fow_win_rate();
+    if first_player_win_rate == 1.0 {
+        eprintln!("1p win");
+    } else if first_player_win_rate == 0.0 {
+        eprintln!("2p win");
+    } else {
+        eprintln!("draw");
+    }
+    first_player_win_rate
+}
+
+// opening_state(i)からのゲームをgame_number×2(先手後手を交代)回プレイしてaisの0番目のAIの勝率を表示する。
+pub fn test_first_player_win_rate<S: ConnectFourState>(
+    new_ais: &AIPairFactory<S>,
+    game_number: usize,
+    seed: Option<u64>,
+    thread_number: usize,
+    sprt: Option<Sprt>,
+) -> MatchResult {
+    battle::test_first_player_win_rate(new_ais, game_number, opening_state::<S>, seed, thread_number, sprt, |_| {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::new_rng;
+
+    #[test]
+    fn opening_states_differ_across_games() {
+        let hashes: Vec<u64> = (0..100).map(|i| opening_state::<BitboardState>(i).zobrist_hash()).collect();
+        // 手順前後で同じ盤面になることはあるので、ほとんどのゲームが違う盤面から始まることを確かめる
+        let distinct: std::collections::HashSet<u64> = hashes.iter().copied().collect();
+        assert!(distinct.len() >= 95, "only {} distinct openings in 100 games", distinct.len());
+        for i in 0..100 {
+            let (array, bitboard) = (opening_state::<ArrayState>(i), opening_state::<BitboardState>(i));
+            assert_eq!(array.zobrist_hash(), bitboard.zobrist_hash());
+            assert_eq!(bitboard.turn, OPENING_MOVES);
+            assert!(!bitboard.is_done());
+        }
+        assert_eq!(opening_state::<BitboardState>(7).zobrist_hash(), hashes[7]);
+    }
+
+    #[test]
+    fn array_and_bitboard_states_agree_along_random_games() {
+        let mut rng = new_rng(Some(0));
+        for game in 0..500 {
+            let mut array = ArrayState::new();
+            let mut bitboard = BitboardState::new();
+            loop {
+                let message = format!("game {} turn {}", game, array.turn);
+                assert_eq!(array.legal_actions(), bitboard.legal_actions(), "{}", message);
+                assert_eq!(array.winner(), bitboard.winner(), "{}", message);
+                assert_eq!(array.zobrist_hash(), bitboard.zobrist_hash(), "{}", message);
+                assert_eq!(array.is_done(), bitboard.is_done(), "{}", message);
+                assert_eq!(array.evaluate(), bitboard.evaluate(), "{}", message);
+                for y in 0..H {
+                    for x in 0..W {
+                        assert_eq!(array.stone(y, x), bitboard.stone(y, x), "{} ({}, {})", message, y, x);
+                    }
+                }
+                if array.is_done() {
+                    break;
+                }
+                let legal_actions = array.legal_actions();
+                let action = legal_actions[rng.gen_range(0, legal_actions.len())];
+                array.advance(action);
+                bitboard.advance(action);
+            }
+        }
+    }
+}
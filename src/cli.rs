@@ -0,0 +1,476 @@
+// 各章のバイナリで共通に使うコマンドライン引数の処理
+// `--key value` 形式のオプションと、それ以外の位置引数(AIの名前)を受け付ける
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::ch4::{self, Ch4AIFunction};
+use crate::mcts::{
+    mcts_action, mcts_action_with_time_threshold, primitive_monte_carlo_action, root_parallel_mcts_action,
+    root_parallel_mcts_action_with_time_threshold,
+};
+use crate::search::{
+    alpha_beta_action, beam_search_action, beam_search_action_dedup, beam_search_action_with_time_threshold,
+    beam_search_action_with_time_threshold_dedup, chokudai_search_action, chokudai_search_action_dedup,
+    chokudai_search_action_with_time_threshold, chokudai_search_action_with_time_threshold_dedup, greedy_action,
+    iterative_deepening_action, mini_max_action, random_action,
+};
+use crate::simultaneous_search::{self, duct_action, duct_action_with_time_threshold};
+use crate::statistics::Sprt;
+use crate::thunder::{thunder_search_action, thunder_search_action_with_time_threshold};
+use crate::transposition_table::alpha_beta_action_with_table;
+use crate::{ch6, AIFunction, AlternateGameState, HashedGameState, RandomGenerator, StringAIPair};
+
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    pub positional: Vec<String>,
+    pub help: bool,
+    options: HashMap<String, String>,
+}
+impl Args {
+    // knownに含まれないオプションはエラーにする
+    pub fn parse<I: IntoIterator<Item = String>>(args: I, known: &[&str]) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                parsed.help = true;
+            } else if let Some(key) = arg.strip_prefix("--") {
+                if !known.contains(&key) {
+                    return Err(format!("unknown option: --{}", key));
+                }
+                let Some(value) = args.next() else {
+                    return Err(format!("missing value for --{}", key));
+                };
+                parsed.options.insert(key.to_string(), value);
+            } else {
+                parsed.positional.push(arg);
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn from_env(known: &[&str]) -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1), known)
+    }
+
+    // 引数が不正な場合や--helpが指定された場合は使い方を表示して終了する
+    pub fn from_env_or_exit(known: &[&str], usage: &str) -> Self {
+        let args = Self::from_env(known).unwrap_or_else(|e| exit_with_usage(usage, Some(&e)));
+        if args.help {
+            exit_with_usage(usage, None);
+        }
+        args
+    }
+
+    pub fn get<T: FromStr>(&self, key: &str, default: T) -> Result<T, String> {
+        match self.options.get(key) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("invalid value for --{}: {}", key, value)),
+            None => Ok(default),
+        }
+    }
+
+    // "none"を指定した場合は乱数で初期化する
+    pub fn get_seed(&self, default: Option<u64>) -> Result<Option<u64>, String> {
+        match self.options.get("seed").map(String::as_str) {
+            Some("none") => Ok(None),
+            Some(_) => self.get("seed", 0).map(Some),
+            None => Ok(default),
+        }
+    }
+
+    // ファイル名などの文字列のオプション。指定されていなければNone
+    pub fn get_path(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(String::as_str)
+    }
+
+    // --sprt-eloが指定されていれば、先頭のAIがその分だけ強いか弱いかをSPRTで判定する
+    pub fn get_sprt(&self) -> Result<Option<Sprt>, String> {
+        if !self.options.contains_key("sprt-elo") {
+            return Ok(None);
+        }
+        let elo = self.get("sprt-elo", 0.0)?;
+        let alpha = self.get("sprt-alpha", 0.05)?;
+        if elo <= 0.0 || alpha <= 0.0 || alpha >= 0.5 {
+            return Err(format!("invalid SPRT parameters: elo {}, alpha {}", elo, alpha));
+        }
+        Ok(Some(Sprt::symmetric(elo, alpha)))
+    }
+
+    // index番目の位置引数。指定されていなければdefault
+    pub fn ai_name<'a>(&'a self, index: usize, default: &'a str) -> &'a str {
+        self.positional.get(index).map(String::as_str).unwrap_or(default)
+    }
+}
+
+// 使い方とAIの一覧を表示して終了する
+pub fn exit_with_usage(usage: &str, error: Option<&str>) -> ! {
+    match error {
+        Some(error) => {
+            eprintln!("error: {}", error);
+            eprintln!("{}", usage);
+            std::process::exit(2);
+        }
+        None => {
+            println!("{}", usage);
+            std::process::exit(0);
+        }
+    }
+}
+
+// 名前や引数が正しいかをnew_aiで一度確かめてから、並列に対戦させるときにスレッドごとに
+// 同じAIを作り直すための関数を返す
+pub fn ai_factory<T, F>(args: &Args, new_ai: F) -> Result<impl Fn() -> T + Sync, String>
+where
+    F: Fn(&Args) -> Result<T, String> + Sync,
+{
+    new_ai(args)?;
+    let args = args.clone();
+    Ok(move || new_ai(&args).unwrap())
+}
+
+// 対戦の成績を検定するオプションとその説明
+pub const SPRT_OPTIONS: [&str; 2] = ["sprt-elo", "sprt-alpha"];
+pub const SPRT_OPTIONS_USAGE: &str = "  --sprt-elo E      stop the match once SPRT decides whether the first AI
+                    is E Elo stronger or E Elo weaker than the second
+  --sprt-alpha A    error probability of SPRT (default: 0.05)";
+
+pub const SINGLE_PLAYER_AI_NAMES: [&str; 6] = ["random", "greedy", "beam", "beam-time", "chokudai", "chokudai-time"];
+
+// --width, --depth, --number, --time, --dedup を読んで1人ゲーム(chapter3, chapter7)のAIを作る
+// 重複を除いた探索で捨てた状態の数はsearch::take_droppedで集計する
+pub fn single_player_ai<S: HashedGameState + 'static>(name: &str, args: &Args) -> Result<StringAIPair<S>, String> {
+    let dedup = args.get("dedup", false)?;
+    let ai: Rc<AIFunction<S>> = match name {
+        "random" => Rc::new(random_action::<S, RandomGenerator>),
+        "greedy" => Rc::new(|state, _rng| greedy_action(state)),
+        "beam" => {
+            let beam_width = args.get("width", 10)?;
+            let beam_depth = args.get("depth", 10)?;
+            if dedup {
+                Rc::new(move |state, _rng| beam_search_action_dedup(state, beam_width, beam_depth).0)
+            } else {
+                Rc::new(move |state, _rng| beam_search_action(state, beam_width, beam_depth))
+            }
+        }
+        "beam-time" => {
+            let beam_width = args.get("width", 5)?;
+            let time_threshold = args.get("time", 0.001)?;
+            if dedup {
+                Rc::new(move |state, _rng| {
+                    beam_search_action_with_time_threshold_dedup(state, beam_width, time_threshold).0
+                })
+            } else {
+                Rc::new(move |state, _rng| beam_search_action_with_time_threshold(state, beam_width, time_threshold))
+            }
+        }
+        "chokudai" => {
+            let beam_width = args.get("width", 1)?;
+            let beam_depth = args.get("depth", 10)?;
+            let beam_number = args.get("number", 10)?;
+            if dedup {
+                Rc::new(move |state, _rng| chokudai_search_action_dedup(state, beam_width, beam_depth, beam_number).0)
+            } else {
+                Rc::new(move |state, _rng| chokudai_search_action(state, beam_width, beam_depth, beam_number))
+            }
+        }
+        "chokudai-time" => {
+            let beam_width = args.get("width", 1)?;
+            let beam_depth = args.get("depth", 10)?;
+            let time_threshold = args.get("time", 0.01)?;
+            if dedup {
+                Rc::new(move |state, _rng| {
+                    chokudai_search_action_with_time_threshold_dedup(state, beam_width, beam_depth, time_threshold).0
+                })
+            } else {
+                Rc::new(move |state, _rng| {
+                    chokudai_search_action_with_time_threshold(state, beam_width, beam_depth, time_threshold)
+                })
+            }
+        }
+        _ => return Err(format!("unknown AI: {}", name)),
+    };
+    Ok((name.to_string(), ai))
+}
+
+pub const CH4_AI_NAMES: [&str; 5] = ["random", "hill-climb", "hill-climb-time", "annealing", "annealing-time"];
+
+// chapter4のAIが読むオプションとその説明
+pub const CH4_AI_OPTIONS: [&str; 11] = [
+    "number",
+    "time",
+    "restarts",
+    "threads",
+    "start-temp",
+    "end-temp",
+    "schedule",
+    "acceptance",
+    "objective",
+    "operators",
+    "radius",
+];
+pub const CH4_AI_OPTIONS_USAGE: &str = "  --number N        number of iterations of hill-climb and annealing
+                    (default: 100000)
+  --time SEC        time limit of hill-climb-time and annealing-time per game (default: 1.0)
+  --restarts N      split the iterations or the time into N independent runs and keep the best placement
+                    (default: 1)
+  --threads N       number of threads sharing the runs of --restarts (default: 1)
+  --start-temp T    start temperature of annealing (default: 100)
+  --end-temp T      end temperature of annealing (default: 0)
+  --schedule S      how annealing lowers the temperature: linear, exponential (positive temperatures),
+                    logarithmic, reheating:CYCLES (default: linear)
+  --acceptance A    when annealing accepts a worse placement (default: metropolis)
+                    metropolis: with probability exp(-loss / temperature)
+                    threshold: if the loss is at most the temperature
+                    late:L: if no worse than the score L iterations ago (late acceptance hill climbing)
+                    deluge: if no worse than the best score minus the temperature (great deluge)
+  --objective O     max or min: whether annealing maximises or minimises the score (default: max)
+  --operators LIST  neighbourhood operators of hill-climb and annealing with their selection weights,
+                    e.g. teleport:1,shift:3,hotspot:1 (default: teleport:1)
+                    teleport: move a character anywhere, shift: move a character by up to R cells,
+                    hotspot: move a character near a high-value cell
+  --radius R        maximum displacement of shift and hotspot in each axis (default: 2)";
+
+// --number, --time, --restarts, --threads, --start-temp, --end-temp, --schedule, --acceptance, --objective,
+// --operators, --radius を読んでchapter4のAIを作る
+// 局所探索のAIは、近傍の操作ごとの採用数をch4::take_operator_statsで集計できるように記録する
+pub fn ch4_ai(name: &str, args: &Args) -> Result<Rc<Ch4AIFunction>, String> {
+    // --restarts回に分けるときは、反復回数は回数で割り、時間は各スレッドが順に行う回数で割る
+    let restarts: usize = args.get("restarts", 1)?;
+    let thread_number: usize = args.get("threads", 1)?;
+    if restarts == 0 {
+        return Err("--restarts must be positive".to_string());
+    }
+    let waves = restarts.div_ceil(thread_number.max(1));
+    let number = (args.get("number", 100000)? / restarts).max(1);
+    let time_threshold = args.get("time", 1.0)? / waves as f64;
+    let ai: Rc<Ch4AIFunction> = match name {
+        "random" => Rc::new(ch4::random_action::<RandomGenerator>),
+        "hill-climb" => {
+            let neighborhood = ch4_neighborhood(args)?;
+            ch4_local_search(restarts, thread_number, ch4::Objective::MAXIMIZE, move |state, rng| {
+                ch4::hill_climb(state, number, &neighborhood, rng)
+            })
+        }
+        "hill-climb-time" => {
+            let neighborhood = ch4_neighborhood(args)?;
+            ch4_local_search(restarts, thread_number, ch4::Objective::MAXIMIZE, move |state, rng| {
+                ch4::hill_climb_with_time_threshold(state, time_threshold, &neighborhood, rng)
+            })
+        }
+        "annealing" => {
+            let config = ch4_annealing_config(args)?;
+            let neighborhood = ch4_neighborhood(args)?;
+            ch4_local_search(restarts, thread_number, config.objective, move |state, rng| {
+                ch4::simulated_annealing(state, number, &config, &neighborhood, rng)
+            })
+        }
+        "annealing-time" => {
+            let config = ch4_annealing_config(args)?;
+            let neighborhood = ch4_neighborhood(args)?;
+            ch4_local_search(restarts, thread_number, config.objective, move |state, rng| {
+                ch4::simulated_annealing_with_time_threshold(state, time_threshold, &config, &neighborhood, rng)
+            })
+        }
+        _ => return Err(format!("unknown AI: {}", name)),
+    };
+    Ok(ai)
+}
+
+// restartsが1ならsearchをそのまま、2以上ならmulti_startでthread_number本のスレッドに分けて行うAI
+fn ch4_local_search<F>(restarts: usize, thread_number: usize, objective: ch4::Objective, search: F) -> Rc<Ch4AIFunction>
+where
+    F: Fn(&ch4::MazeState, &mut RandomGenerator) -> (ch4::MazeState, ch4::OperatorStats) + Sync + 'static,
+{
+    Rc::new(move |state, rng| {
+        let (state, stats) = if restarts == 1 {
+            search(state, rng)
+        } else {
+            ch4::multi_start(state, restarts, thread_number, objective, rng, &search)
+        };
+        ch4::record_operator_stats(&stats);
+        state
+    })
+}
+
+fn ch4_annealing_config(args: &Args) -> Result<ch4::AnnealingConfig, String> {
+    let default = ch4::AnnealingConfig::default();
+    let config = ch4::AnnealingConfig {
+        start_temp: args.get("start-temp", default.start_temp)?,
+        end_temp: args.get("end-temp", default.end_temp)?,
+        schedule: ch4::Schedule::parse(&args.get("schedule", "linear".to_string())?)?,
+        acceptance: ch4::Acceptance::parse(&args.get("acceptance", "metropolis".to_string())?)?,
+        objective: ch4::Objective::parse(&args.get("objective", "max".to_string())?)?,
+    };
+    config.validate()?;
+    Ok(config)
+}
+
+fn ch4_neighborhood(args: &Args) -> Result<ch4::Neighborhood, String> {
+    let radius = args.get("radius", ch4::Neighborhood::default().radius)?;
+    ch4::Neighborhood::parse(&args.get("operators", "teleport:1".to_string())?, radius)
+}
+
+pub const ALTERNATE_AI_NAMES: [&str; 12] = [
+    "random",
+    "min-max",
+    "alpha-beta",
+    "alpha-beta-time",
+    "alpha-beta-tt",
+    "primitive-mc",
+    "mcts",
+    "mcts-time",
+    "mcts-parallel",
+    "mcts-parallel-time",
+    "thunder",
+    "thunder-time",
+];
+
+// 2人交互ゲームのAIが読むオプションとその説明
+pub const ALTERNATE_AI_OPTIONS: [&str; 7] =
+    ["depth", "table-bits", "playout", "c", "expand-threshold", "time", "threads"];
+
+pub fn alternate_ai_options_usage(default_depth: usize) -> String {
+    format!(
+        "  --depth N         search depth of min-max, alpha-beta and alpha-beta-tt (default: {})
+  --table-bits N    alpha-beta-tt uses a transposition table of 2^N entries (default: 16)
+  --playout N       number of playouts of primitive-mc, mcts and thunder, per thread for mcts-parallel
+                    (default: 1000)
+  --c C             exploration constant of mcts (default: 1.0)
+  --expand-threshold N
+                    number of visits before a node of mcts is expanded (default: 10)
+  --time SEC        time limit per action of alpha-beta-time, mcts-time, mcts-parallel-time and thunder-time
+                    (default: 0.01)
+  --threads N       number of threads of mcts-parallel (default: 4)",
+        default_depth
+    )
+}
+
+// --depth, --playout, --c, --expand-threshold, --time, --threads を読んで2人交互ゲーム(chapter5, chapter8)のAIを作る
+pub fn alternate_ai<S: AlternateGameState + HashedGameState + Send + Sync + 'static>(
+    name: &str,
+    args: &Args,
+    default_depth: usize,
+) -> Result<StringAIPair<S>, String> {
+    let ai: StringAIPair<S> = match name {
+        "random" => (name.to_string(), Rc::new(random_action::<S, RandomGenerator>)),
+        "min-max" => {
+            let depth = args.get("depth", default_depth)?;
+            (name.to_string(), Rc::new(move |state, _rng| mini_max_action(state, depth)))
+        }
+        "alpha-beta" => {
+            let depth = args.get("depth", default_depth)?;
+            (name.to_string(), Rc::new(move |state, _rng| alpha_beta_action(state, depth)))
+        }
+        "alpha-beta-tt" => {
+            let depth = args.get("depth", default_depth)?;
+            let table_bits = args.get("table-bits", 16)?;
+            (name.to_string(), Rc::new(move |state, _rng| alpha_beta_action_with_table(state, depth, table_bits)))
+        }
+        "alpha-beta-time" => {
+            let time_threshold = args.get("time", 0.01)?;
+            (name.to_string(), Rc::new(move |state, _rng| iterative_deepening_action(state, time_threshold).0))
+        }
+        "primitive-mc" => {
+            let playout_number = args.get("playout", 1000)?;
+            (name.to_string(), Rc::new(move |state, rng| primitive_monte_carlo_action(state, playout_number, rng)))
+        }
+        "mcts" => {
+            let playout_number = args.get("playout", 1000)?;
+            let c = args.get("c", 1.0)?;
+            let expand_threshold = args.get("expand-threshold", 10)?;
+            (name.to_string(), Rc::new(move |state, rng| mcts_action(state, playout_number, c, expand_threshold, rng)))
+        }
+        "mcts-time" => {
+            let time_threshold = args.get("time", 0.01)?;
+            let c = args.get("c", 1.0)?;
+            let expand_threshold = args.get("expand-threshold", 10)?;
+            (
+                name.to_string(),
+                Rc::new(move |state, rng| {
+                    mcts_action_with_time_threshold(state, time_threshold, c, expand_threshold, rng)
+                }),
+            )
+        }
+        "mcts-parallel" => {
+            let playout_number = args.get("playout", 1000)?;
+            let c = args.get("c", 1.0)?;
+            let expand_threshold = args.get("expand-threshold", 10)?;
+            let thread_number = args.get("threads", 4)?;
+            (
+                name.to_string(),
+                Rc::new(move |state, rng| {
+                    root_parallel_mcts_action(state, playout_number, c, expand_threshold, thread_number, rng)
+                }),
+            )
+        }
+        "mcts-parallel-time" => {
+            let time_threshold = args.get("time", 0.01)?;
+            let c = args.get("c", 1.0)?;
+            let expand_threshold = args.get("expand-threshold", 10)?;
+            let thread_number = args.get("threads", 4)?;
+            (
+                name.to_string(),
+                Rc::new(move |state, rng| {
+                    root_parallel_mcts_action_with_time_threshold(
+                        state,
+                        time_threshold,
+                        c,
+                        expand_threshold,
+                        thread_number,
+                        rng,
+                    )
+                }),
+            )
+        }
+        "thunder" => {
+            let playout_number = args.get("playout", 1000)?;
+            (name.to_string(), Rc::new(move |state, _rng| thunder_search_action(state, playout_number)))
+        }
+        "thunder-time" => {
+            let time_threshold = args.get("time", 0.01)?;
+            (
+                name.to_string(),
+                Rc::new(move |state, _rng| thunder_search_action_with_time_threshold(state, time_threshold)),
+            )
+        }
+        _ => return Err(format!("unknown AI: {}", name)),
+    };
+    Ok(ai)
+}
+
+pub const CH6_AI_NAMES: [&str; 4] = ["random", "primitive-mc", "duct", "duct-time"];
+
+// --playout, --c, --expand-threshold, --time を読んでchapter6のAIを作る
+pub fn ch6_ai(name: &str, args: &Args) -> Result<ch6::StringSimultaneousAIPair, String> {
+    let ai: Rc<ch6::SimultaneousAIFunction> = match name {
+        "random" => Rc::new(simultaneous_search::random_action::<ch6::MazeState, RandomGenerator>),
+        "primitive-mc" => {
+            let playout_number = args.get("playout", 1000)?;
+            Rc::new(move |state, player_id, rng| {
+                simultaneous_search::primitive_monte_carlo_action(state, player_id, playout_number, rng)
+            })
+        }
+        "duct" => {
+            let playout_number = args.get("playout", 1000)?;
+            let c = args.get("c", 1.0)?;
+            let expand_threshold = args.get("expand-threshold", 5)?;
+            Rc::new(move |state, player_id, rng| {
+                duct_action(state, player_id, playout_number, c, expand_threshold, rng)
+            })
+        }
+        "duct-time" => {
+            let time_threshold = args.get("time", 0.01)?;
+            let c = args.get("c", 1.0)?;
+            let expand_threshold = args.get("expand-threshold", 5)?;
+            Rc::new(move |state, player_id, rng| {
+                duct_action_with_time_threshold(state, player_id, time_threshold, c, expand_threshold, rng)
+            })
+        }
+        _ => return Err(format!("unknown AI: {}", name)),
+    };
+    Ok((name.to_string(), ai))
+}
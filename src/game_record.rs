@@ -0,0 +1,399 @@
+// chapter5のゲームの棋譜。ファイルに保存して、後から1手ずつ再生できる
+//
+// 形式は1行に1項目で、#以降はコメントとして読み飛ばす
+//   players min-max alpha-beta   先手(1p)と後手(2p)のAIの名前
+//   seed 314                     盤面を生成したseed(不明ならnone)
+//   size 5 5 10                  盤面の高さ、幅、終了ターン
+//   grid                         続くH行が盤面の点数(0から9の数字)。省略した場合はseedから生成する
+//   01234
+//   ...
+//   characters 2 0 2 4           先手、後手の初期位置(y x)。gridを省略した場合は不要
+//   actions 0 2 3 ...            先手から交互に選んだ行動(dx, dyの添字)
+//   scores 12 9                  終局時の先手、後手の得点(省略可)
+//   result 1p win                1p win, 2p win, drawのいずれか(省略可)
+
+use crate::ch5::MazeState;
+use crate::{AlternateGameState, Coord, GameState, ScoreType};
+
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    pub players: [String; 2],
+    pub seed: Option<u64>,
+    pub initial_state: MazeState,
+    pub actions: Vec<usize>,
+    pub scores: Option<[ScoreType; 2]>,
+}
+impl GameRecord {
+    pub fn new(players: [String; 2], seed: Option<u64>, initial_state: MazeState) -> Self {
+        GameRecord {
+            players,
+            seed,
+            initial_state,
+            actions: Vec::new(),
+            scores: None,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let state = &self.initial_state;
+        let mut text = format!("players {} {}\n", self.players[0], self.players[1]);
+        match self.seed {
+            Some(seed) => text += &format!("seed {}\n", seed),
+            None => text += "seed none\n",
+        }
+        text += &format!("size {} {} {}\n", state.h, state.w, state.end_turn);
+        text += "grid\n";
+        for row in state.grid.iter() {
+            text += &row.iter().map(|cell| cell.to_string()).collect::<String>();
+            text += "\n";
+        }
+        // 初期状態では先手がcharacters[0]
+        let (first, second) = (&state.characters[0], &state.characters[1]);
+        text += &format!("characters {} {} {} {}\n", first.y, first.x, second.y, second.x);
+        let actions: Vec<String> = self.actions.iter().map(|action| action.to_string()).collect();
+        text += &format!("actions {}\n", actions.join(" "));
+        if let Some(scores) = self.scores {
+            text += &format!("scores {} {}\n", scores[0], scores[1]);
+            text += &format!("result {}\n", result_name(&scores));
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut players = None;
+        let mut seed = None;
+        let mut size = None;
+        let mut grid: Option<Vec<Vec<usize>>> = None;
+        let mut positions = None;
+        let mut actions = Vec::new();
+        let mut scores = None;
+        let mut result = None;
+
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.split('#').next().unwrap().trim()))
+            .filter(|(_, line)| !line.is_empty());
+        while let Some((line_number, line)) = lines.next() {
+            let error = |message: String| format!("line {}: {}", line_number, message);
+            let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let values: Vec<&str> = rest.split_whitespace().collect();
+            match key {
+                "players" => {
+                    let [first, second] = values[..] else {
+                        return Err(error("players needs 2 names".to_string()));
+                    };
+                    players = Some([first.to_string(), second.to_string()]);
+                }
+                "seed" => {
+                    seed = match values[..] {
+                        ["none"] => None,
+                        [value] => Some(value.parse().map_err(|_| error(format!("invalid seed: {}", value)))?),
+                        _ => return Err(error("seed needs a number or none".to_string())),
+                    };
+                }
+                "size" => {
+                    let numbers = parse_numbers(&values).map_err(error)?;
+                    let [h, w, end_turn] = numbers[..] else {
+                        return Err(error("size needs height, width and end turn".to_string()));
+                    };
+                    if h == 0 || w == 0 {
+                        return Err(error(format!("invalid size: {}x{}", h, w)));
+                    }
+                    size = Some((h, w, end_turn));
+                }
+                "grid" => {
+                    let Some((h, w, _)) = size else {
+                        return Err(error("grid must come after size".to_string()));
+                    };
+                    let mut rows = Vec::new();
+                    for _ in 0..h {
+                        let Some((line_number, row)) = lines.next() else {
+                            return Err(error(format!("grid needs {} rows", h)));
+                        };
+                        let error = |message: String| format!("line {}: {}", line_number, message);
+                        if row.chars().count() != w {
+                            return Err(error(format!("grid row needs {} digits: {}", w, row)));
+                        }
+                        let row = row
+                            .chars()
+                            .map(|c| c.to_digit(10).map(|digit| digit as usize))
+                            .collect::<Option<Vec<usize>>>()
+                            .ok_or_else(|| error(format!("grid row must consist of digits: {}", row)))?;
+                        rows.push(row);
+                    }
+                    grid = Some(rows);
+                }
+                "characters" => {
+                    let numbers = parse_numbers(&values).map_err(error)?;
+                    let [y0, x0, y1, x1] = numbers[..] else {
+                        return Err(error("characters needs 2 positions (y x)".to_string()));
+                    };
+                    positions = Some((line_number, [Coord { y: y0, x: x0 }, Coord { y: y1, x: x1 }]));
+                }
+                "actions" => actions = parse_numbers(&values).map_err(error)?,
+                "scores" => {
+                    let numbers = values
+                        .iter()
+                        .map(|value| value.parse().map_err(|_| error(format!("invalid score: {}", value))))
+                        .collect::<Result<Vec<ScoreType>, String>>()?;
+                    let [first, second] = numbers[..] else {
+                        return Err(error("scores needs 2 numbers".to_string()));
+                    };
+                    scores = Some([first, second]);
+                }
+                "result" => {
+                    if !["1p win", "2p win", "draw"].contains(&rest.trim()) {
+                        return Err(error(format!("unknown result: {}", rest.trim())));
+                    }
+                    result = Some((line_number, rest.trim().to_string()));
+                }
+                _ => return Err(error(format!("unknown item: {}", key))),
+            }
+        }
+
+        let players = players.ok_or("players is missing")?;
+        let (h, w, end_turn) = size.ok_or("size is missing")?;
+        let initial_state = match grid {
+            Some(grid) => {
+                let (line_number, positions) = positions.ok_or("characters is missing")?;
+                for position in positions.iter() {
+                    if position.y >= h || position.x >= w {
+                        return Err(format!(
+                            "line {}: character ({}, {}) is outside the {}x{} grid",
+                            line_number, position.y, position.x, h, w
+                        ));
+                    }
+                }
+                MazeState::from_grid(grid, end_turn, positions)
+            }
+            None => {
+                if seed.is_none() {
+                    return Err("either grid or seed is needed".to_string());
+                }
+                MazeState::with_size(seed, h, w, end_turn)
+            }
+        };
+        if let (Some(scores), Some((line_number, result))) = (scores, result) {
+            if result != result_name(&scores) {
+                return Err(format!(
+                    "line {}: result {} does not match scores {} {}",
+                    line_number, result, scores[0], scores[1]
+                ));
+            }
+        }
+        Ok(GameRecord {
+            players,
+            seed,
+            initial_state,
+            actions,
+            scores,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_text()).map_err(|e| format!("cannot write {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // 初期状態から1手ずつ進め、進めた後の状態をon_stepに渡す
+    // 合法でない行動や終局後の行動があればその手数を、終局しなかったり得点が記録と違えばその旨を返す
+    pub fn replay(&self, mut on_step: impl FnMut(usize, &MazeState)) -> Result<MazeState, String> {
+        let mut state = self.initial_state.clone();
+        for (index, &action) in self.actions.iter().enumerate() {
+            if state.is_done() {
+                return Err(format!("action {} ({}) is played after the game ended", index + 1, action));
+            }
+            if !state.legal_actions().contains(&action) {
+                return Err(format!(
+                    "action {} ({}) by {}p is not legal at turn {}",
+                    index + 1,
+                    action,
+                    if state.is_first() { 1 } else { 2 },
+                    state.turn
+                ));
+            }
+            state.advance(action);
+            on_step(index, &state);
+        }
+        if !state.is_done() {
+            return Err(format!(
+                "the game ends at turn {} but only {} actions are recorded",
+                state.end_turn, state.turn
+            ));
+        }
+        let scores = state.first_player_scores();
+        if let Some(recorded) = self.scores {
+            if recorded != scores {
+                return Err(format!(
+                    "recorded scores {} {} differ from replayed scores {} {}",
+                    recorded[0], recorded[1], scores[0], scores[1]
+                ));
+            }
+        }
+        Ok(state)
+    }
+}
+
+fn parse_numbers(values: &[&str]) -> Result<Vec<usize>, String> {
+    values
+        .iter()
+        .map(|value| value.parse().map_err(|_| format!("invalid number: {}", value)))
+        .collect()
+}
+
+// 先手、後手の得点から見た勝敗
+pub fn result_name(scores: &[ScoreType; 2]) -> &'static str {
+    match scores[0].cmp(&scores[1]) {
+        std::cmp::Ordering::Greater => "1p win",
+        std::cmp::Ordering::Less => "2p win",
+        std::cmp::Ordering::Equal => "draw",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::random_action;
+    use crate::{new_rng, GameState};
+
+    // seedの盤面で両者がランダムに終局まで打った棋譜
+    fn random_record(seed: u64) -> GameRecord {
+        let initial_state = MazeState::new(Some(seed));
+        let mut record = GameRecord::new(["random".to_string(), "greedy".to_string()], Some(seed), initial_state);
+        let mut state = record.initial_state.clone();
+        let mut rng = new_rng(Some(seed));
+        while !state.is_done() {
+            let action = random_action(&state, &mut rng);
+            record.actions.push(action);
+            state.advance(action);
+        }
+        record.scores = Some(state.first_player_scores());
+        record
+    }
+
+    fn assert_same_state(a: &MazeState, b: &MazeState) {
+        assert_eq!((a.h, a.w, a.end_turn, a.turn), (b.h, b.w, b.end_turn, b.turn));
+        assert_eq!(a.grid, b.grid);
+        let positions = |state: &MazeState| -> Vec<(usize, usize)> {
+            state.characters.iter().map(|character| (character.y, character.x)).collect()
+        };
+        assert_eq!(positions(a), positions(b));
+        assert_eq!(a.hash, b.hash);
+    }
+
+    fn parse_error(text: &str) -> String {
+        GameRecord::parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn round_trip_keeps_the_record() {
+        for seed in 0..20 {
+            let record = random_record(seed);
+            let parsed = GameRecord::parse(&record.to_text()).unwrap();
+            assert_eq!(parsed.players, record.players);
+            assert_eq!(parsed.seed, record.seed);
+            assert_eq!(parsed.actions, record.actions);
+            assert_eq!(parsed.scores, record.scores);
+            assert_same_state(&parsed.initial_state, &record.initial_state);
+            assert_eq!(parsed.to_text(), record.to_text());
+            let replayed = parsed.replay(|_, _| {}).unwrap();
+            assert_eq!(Some(replayed.first_player_scores()), record.scores);
+        }
+    }
+
+    #[test]
+    fn record_without_grid_is_generated_from_seed() {
+        let record = GameRecord::parse("players a b\nseed 3\nsize 5 5 10\nactions\n").unwrap();
+        assert_same_state(&record.initial_state, &MazeState::new(Some(3)));
+    }
+
+    #[test]
+    fn parse_reports_bad_items() {
+        let grid = "players a b\nseed none\nsize 2 3 4\ngrid\n";
+        let cases = [
+            ("players a\n", "line 1: players needs 2 names"),
+            ("players a b\nseed x\n", "line 2: invalid seed: x"),
+            ("players a b\nseed 1 2\n", "line 2: seed needs a number or none"),
+            ("players a b\nsize 5 5\n", "line 2: size needs height, width and end turn"),
+            ("players a b\nsize 0 5 10\n", "line 2: invalid size: 0x5"),
+            ("players a b\nsize 5 x 10\n", "line 2: invalid number: x"),
+            ("players a b\ngrid\n", "line 2: grid must come after size"),
+            (&format!("{}123\n", grid), "line 4: grid needs 2 rows"),
+            (&format!("{}123\n45\n", grid), "line 6: grid row needs 3 digits: 45"),
+            (&format!("{}123\n4a6\n", grid), "line 6: grid row must consist of digits: 4a6"),
+            ("players a b\ncharacters 0 0 1\n", "line 2: characters needs 2 positions (y x)"),
+            ("players a b\nscores 1\n", "line 2: scores needs 2 numbers"),
+            ("players a b\nscores 1 x\n", "line 2: invalid score: x"),
+            ("players a b\nresult win\n", "line 2: unknown result: win"),
+            ("players a b\nmoves 1 2\n", "line 2: unknown item: moves"),
+            ("seed 1\nsize 5 5 10\n", "players is missing"),
+            ("players a b\nseed 1\n", "size is missing"),
+            ("players a b\nseed none\nsize 5 5 10\n", "either grid or seed is needed"),
+            (&format!("{}123\n456\n", grid), "characters is missing"),
+            (
+                &format!("{}123\n456\ncharacters 0 0 1 3\n", grid),
+                "line 7: character (1, 3) is outside the 2x3 grid",
+            ),
+            (
+                "players a b\nseed 1\nsize 5 5 10\nscores 3 4\nresult 1p win\n",
+                "line 5: result 1p win does not match scores 3 4",
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_error(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn replay_reports_bad_actions() {
+        let record = random_record(0);
+        let (y, x) = (record.initial_state.characters[0].y, record.initial_state.characters[0].x);
+        // 先手は左端にいるので左には動けない
+        assert_eq!((y, x), (2, 0));
+        let mut illegal = record.clone();
+        illegal.actions[0] = 2;
+        assert_eq!(illegal.replay(|_, _| {}).unwrap_err(), "action 1 (2) by 1p is not legal at turn 0");
+
+        let mut too_long = record.clone();
+        too_long.actions.push(0);
+        assert_eq!(too_long.replay(|_, _| {}).unwrap_err(), "action 11 (0) is played after the game ended");
+
+        let mut too_short = record.clone();
+        too_short.actions.pop();
+        assert_eq!(
+            too_short.replay(|_, _| {}).unwrap_err(),
+            "the game ends at turn 10 but only 9 actions are recorded"
+        );
+
+        let mut wrong_scores = record.clone();
+        let scores = record.scores.unwrap();
+        wrong_scores.scores = Some([scores[0] + 1, scores[1]]);
+        assert_eq!(
+            wrong_scores.replay(|_, _| {}).unwrap_err(),
+            format!(
+                "recorded scores {} {} differ from replayed scores {} {}",
+                scores[0] + 1,
+                scores[1],
+                scores[0],
+                scores[1]
+            )
+        );
+    }
+
+    #[test]
+    fn saved_record_loads_back() {
+        let record = random_record(5);
+        let path = std::env::temp_dir().join(format!("game_record_test_{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        record.save(path).unwrap();
+        let loaded = GameRecord::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.to_text(), record.to_text());
+        assert!(GameRecord::load(path).unwrap_err().starts_with(&format!("cannot read {}: ", path)));
+    }
+}
@@ -0,0 +1,59 @@
+// 探索アルゴリズムが扱うゲームの状態のインターフェース
+
+use std::rc::Rc;
+
+use crate::{RandomGenerator, ScoreType};
+
+pub trait GameState: Clone {
+    // 現在の手番で選べる行動
+    fn legal_actions(&self) -> Vec<usize>;
+    // 行動を適用して次の状態に進める
+    fn advance(&mut self, action: usize);
+    // ゲームが終了しているか
+    fn is_done(&self) -> bool;
+    // 現在の手番のプレイヤーから見た評価値
+    fn evaluate(&self) -> ScoreType;
+}
+
+// 2人が交互に行動するゲーム
+pub trait AlternateGameState: GameState {
+    // 現在の手番が先手か
+    fn is_first(&self) -> bool;
+    // 終了した状態の先手から見た勝率(勝ち1.0、引き分け0.5、負け0.0)
+    fn get_first_player_score_fow_win_rate(&self) -> f64;
+    // 現在の手番のプレイヤーがどれだけ有利かを0.0から1.0で表したヒューリスティックな評価値
+    fn get_score_rate(&self) -> f64;
+
+    // 終了した状態の現在の手番のプレイヤーから見た勝率
+    fn get_win_rate_of_player_to_move(&self) -> f64 {
+        let win_rate = self.get_first_player_score_fow_win_rate();
+        if self.is_first() {
+            win_rate
+        } else {
+            1.0 - win_rate
+        }
+    }
+}
+
+// 2人が同時に行動するゲーム。player_idは0が先手(1p)、1が後手(2p)
+pub trait SimultaneousGameState: Clone {
+    fn legal_actions(&self, player_id: usize) -> Vec<usize>;
+    // 両者の行動を同時に適用して次の状態に進める
+    fn advance(&mut self, action0: usize, action1: usize);
+    fn is_done(&self) -> bool;
+    // 終了した状態の先手から見た勝率(勝ち1.0、引き分け0.5、負け0.0)
+    fn get_first_player_score_fow_win_rate(&self) -> f64;
+}
+
+// Zobrist hashなどで局面を識別できるゲーム
+pub trait HashedGameState: GameState {
+    fn zobrist_hash(&self) -> u64;
+}
+
+// 乱数を使うAIは引数の乱数生成器だけを使い、同じ乱数列なら同じ行動を返す
+pub type AIFunction<S> = dyn Fn(&S, &mut RandomGenerator) -> usize;
+pub type StringAIPair<S> = (String, Rc<AIFunction<S>>);
+
+// 並列に対戦させるとき、スレッドごとに同じAIを作り直すための関数
+pub type AIFactory<S> = dyn Fn() -> StringAIPair<S> + Sync;
+pub type AIPairFactory<S> = dyn Fn() -> [StringAIPair<S>; 2] + Sync;
@@ -0,0 +1,51 @@
+// [世界四連覇AIエンジニアがゼロから教えるゲーム木探索入門] の各章で共通に使う部品
+// thunder(@thun_c)さんのコードを参考にしました
+
+#![allow(non_snake_case, non_upper_case_globals)]
+
+pub mod battle;
+pub mod bitboard;
+pub mod ch3;
+pub mod ch4;
+pub mod ch5;
+pub mod ch6;
+pub mod ch7;
+pub mod ch8;
+pub mod cli;
+pub mod game_record;
+pub mod game_state;
+pub mod maze_text;
+pub mod mcts;
+pub mod random;
+pub mod search;
+pub mod simultaneous_search;
+pub mod statistics;
+pub mod thunder;
+pub mod time_keeper;
+pub mod tournament;
+pub mod transposition_table;
+pub mod worker_pool;
+pub mod zobrist;
+
+pub use game_state::{
+    AIFactory, AIFunction, AIPairFactory, AlternateGameState, GameState, HashedGameState, SimultaneousGameState,
+    StringAIPair,
+};
+pub use random::{new_rng, RandomGenerator};
+pub use time_keeper::TimeKeeper;
+
+pub type ScoreType = isize;
+
+pub const dx: [isize; 4] = [1, 0, -1, 0];
+pub const dy: [isize; 4] = [0, 1, 0, -1];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Coord {
+    pub y: usize,
+    pub x: usize,
+}
+impl Coord {
+    pub fn new() -> Self {
+        Coord { y: 0, x: 0 }
+    }
+}
@@ -0,0 +1,345 @@
+// chapter3, chapter4, chapter5の迷路を読み書きするテキスト形式
+//
+//   H W T N        盤面の高さ、幅、残りのターン数、キャラクターの数
+//   y x            N行。各キャラクターの位置(0始まり)
+//   0123...        H行。各マスの点数を表すW個の数字(0から9)
+//
+// 空行と、#以降はコメントとして読み飛ばす
+// 途中の状態を書き出した場合は、得点を0に戻して残りのターン数だけプレイする問題になる
+
+use crate::Coord;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MazeText {
+    pub height: usize,
+    pub width: usize,
+    pub end_turn: usize,
+    pub characters: Vec<Coord>,
+    pub grid: Vec<Vec<usize>>,
+}
+impl MazeText {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.split('#').next().unwrap().trim()))
+            .filter(|(_, line)| !line.is_empty());
+        let mut next_line =
+            |what: &str| lines.next().ok_or_else(|| format!("unexpected end of input: {} is missing", what));
+
+        let (line_number, line) = next_line("the header (H W T N)")?;
+        let header = parse_numbers(line_number, line, &["height", "width", "turns", "number of characters"])?;
+        let (height, width, end_turn, character_number) = (header[0], header[1], header[2], header[3]);
+        if height == 0 || width == 0 {
+            return Err(format!("line {}: the grid must not be empty: {}x{}", line_number, height, width));
+        }
+
+        let mut characters = Vec::new();
+        for id in 0..character_number {
+            let (line_number, line) = next_line(&format!("the position of character {}", id))?;
+            let position = parse_numbers(line_number, line, &["y", "x"])?;
+            let (y, x) = (position[0], position[1]);
+            if y >= height || x >= width {
+                return Err(format!(
+                    "line {}: character {} at ({}, {}) is outside the {}x{} grid",
+                    line_number, id, y, x, height, width
+                ));
+            }
+            characters.push(Coord { y, x });
+        }
+
+        let mut grid = Vec::new();
+        for y in 0..height {
+            let (line_number, line) = next_line(&format!("row {} of the grid", y))?;
+            let row: Vec<char> = line.chars().collect();
+            if row.len() != width {
+                return Err(format!(
+                    "line {}: row {} needs {} digits but has {}: {}",
+                    line_number,
+                    y,
+                    width,
+                    row.len(),
+                    line
+                ));
+            }
+            let mut cells = Vec::new();
+            for (x, c) in row.into_iter().enumerate() {
+                let Some(point) = c.to_digit(10) else {
+                    return Err(format!("line {}: bad digit '{}' at column {} of row {}", line_number, c, x, y));
+                };
+                cells.push(point as usize);
+            }
+            grid.push(cells);
+        }
+
+        if let Some((line_number, line)) = lines.next() {
+            return Err(format!("line {}: unexpected line after the grid: {}", line_number, line));
+        }
+        Ok(MazeText {
+            height,
+            width,
+            end_turn,
+            characters,
+            grid,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} {} {} {}\n", self.height, self.width, self.end_turn, self.characters.len());
+        for character in self.characters.iter() {
+            text += &format!("{} {}\n", character.y, character.x);
+        }
+        for row in self.grid.iter() {
+            text += &row.iter().map(|&cell| std::char::from_digit(cell as u32, 10).unwrap()).collect::<String>();
+            text += "\n";
+        }
+        text
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_text()).map_err(|e| format!("cannot write {}: {}", path, e))
+    }
+
+    // 読み込んだ迷路でキャラクターが動けなければエラーにする
+    pub fn expect_moves(&self) -> Result<(), String> {
+        check_moves(self.height, self.width, self.end_turn)
+    }
+
+    // 読み込んだ状態のキャラクターの数がexpectedでなければエラーにする
+    pub fn expect_characters(&self, expected: usize) -> Result<(), String> {
+        if self.characters.len() != expected {
+            return Err(format!("this game needs {} characters but the maze has {}", expected, self.characters.len()));
+        }
+        Ok(())
+    }
+}
+
+// 1ターン以上あるのに1マスしかなく、どのキャラクターも動けない迷路をエラーにする
+pub fn check_moves(height: usize, width: usize, end_turn: usize) -> Result<(), String> {
+    if height * width == 1 && end_turn > 0 {
+        return Err(format!("no character can move in a 1x1 maze with {} turns", end_turn));
+    }
+    Ok(())
+}
+
+// 1行に空白区切りで並んだnames.len()個の整数を読む
+fn parse_numbers(line_number: usize, line: &str, names: &[&str]) -> Result<Vec<usize>, String> {
+    let values: Vec<&str> = line.split_whitespace().collect();
+    if values.len() != names.len() {
+        return Err(format!(
+            "line {}: expected {} numbers ({}) but found {}: {}",
+            line_number,
+            names.len(),
+            names.join(", "),
+            values.len(),
+            line
+        ));
+    }
+    values
+        .iter()
+        .zip(names)
+        .map(|(value, name)| value.parse().map_err(|_| format!("line {}: invalid {}: {}", line_number, name, value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::random_action;
+    use crate::{ch3, ch4, ch5, new_rng, GameState, HashedGameState};
+
+    const MAZE: &str = "2 3 4 2\n0 0\n1 2\n123\n405\n";
+
+    // テキストを経由して読み書きしても同じ迷路になることを確かめる
+    fn round_trip(maze: &MazeText) -> MazeText {
+        let parsed = MazeText::parse(&maze.to_text()).unwrap();
+        assert_eq!(&parsed, maze);
+        parsed
+    }
+
+    #[test]
+    fn parse_reads_the_maze() {
+        let maze = MazeText::parse(MAZE).unwrap();
+        assert_eq!((maze.height, maze.width, maze.end_turn), (2, 3, 4));
+        assert_eq!(maze.characters, vec![Coord { y: 0, x: 0 }, Coord { y: 1, x: 2 }]);
+        assert_eq!(maze.grid, vec![vec![1, 2, 3], vec![4, 0, 5]]);
+        assert_eq!(maze.to_text(), MAZE);
+    }
+
+    #[test]
+    fn parse_skips_comments_and_blank_lines() {
+        let text = "# comment\n\n2 3 4 2  # H W T N\n0 0\n\n1 2\n123 # row 0\n405\n\n";
+        assert_eq!(MazeText::parse(text).unwrap(), MazeText::parse(MAZE).unwrap());
+    }
+
+    #[test]
+    fn parse_reports_errors() {
+        let cases = [
+            ("", "unexpected end of input: the header (H W T N) is missing"),
+            ("# only a comment\n", "unexpected end of input: the header (H W T N) is missing"),
+            ("2 3 4\n", "line 1: expected 4 numbers (height, width, turns, number of characters) but found 3: 2 3 4"),
+            ("2 3 x 1\n", "line 1: invalid turns: x"),
+            ("2 0 4 1\n", "line 1: the grid must not be empty: 2x0"),
+            ("2 3 4 2\n0 0\n", "unexpected end of input: the position of character 1 is missing"),
+            ("2 3 4 1\n0\n", "line 2: expected 2 numbers (y, x) but found 1: 0"),
+            ("2 3 4 1\n0 -1\n", "line 2: invalid x: -1"),
+            ("2 3 4 1\n2 0\n", "line 2: character 0 at (2, 0) is outside the 2x3 grid"),
+            ("2 3 4 1\n0 0\n123\n", "unexpected end of input: row 1 of the grid is missing"),
+            ("2 3 4 1\n0 0\n123\n4567\n", "line 4: row 1 needs 3 digits but has 4: 4567"),
+            ("2 3 4 1\n0 0\n1a3\n456\n", "line 3: bad digit 'a' at column 1 of row 0"),
+            ("2 3 4 1\n0 0\n123\n456\n789\n", "line 5: unexpected line after the grid: 789"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(MazeText::parse(text).unwrap_err(), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn expect_characters_checks_the_number() {
+        let maze = MazeText::parse(MAZE).unwrap();
+        assert!(maze.expect_characters(2).is_ok());
+        assert_eq!(maze.expect_characters(1).unwrap_err(), "this game needs 1 characters but the maze has 2");
+        assert_eq!(
+            ch3::MazeState::from_maze_text(&maze).unwrap_err(),
+            "this game needs 1 characters but the maze has 2"
+        );
+        let no_character = MazeText::parse("1 2 3 0\n12\n").unwrap();
+        assert_eq!(
+            ch4::MazeState::from_maze_text(&no_character).unwrap_err(),
+            "chapter4 needs at least one character"
+        );
+        assert_eq!(
+            ch5::MazeState::from_maze_text(&no_character).unwrap_err(),
+            "this game needs 2 characters but the maze has 0"
+        );
+    }
+
+    #[test]
+    fn ch3_rejects_mazes_without_moves() {
+        let maze = MazeText::parse("1 1 3 1\n0 0\n5\n").unwrap();
+        assert_eq!(
+            ch3::MazeState::from_maze_text(&maze).unwrap_err(),
+            "no character can move in a 1x1 maze with 3 turns"
+        );
+        let finished = MazeText::parse("1 1 0 1\n0 0\n5\n").unwrap();
+        assert!(ch3::MazeState::from_maze_text(&finished).is_ok());
+        let config = ch3::MazeConfig { height: 0, width: 3, end_turn: 3, max_point: 9 };
+        assert_eq!(config.validate().unwrap_err(), "the maze must not be empty: 0x3");
+    }
+
+    #[test]
+    fn ch4_and_ch5_reject_mazes_without_moves() {
+        let maze = MazeText::parse("1 1 3 2\n0 0\n0 0\n5\n").unwrap();
+        let expected = "no character can move in a 1x1 maze with 3 turns";
+        assert_eq!(maze.expect_moves().unwrap_err(), expected);
+        assert_eq!(ch4::MazeState::from_maze_text(&maze).unwrap_err(), expected);
+        assert_eq!(ch5::MazeState::from_maze_text(&maze).unwrap_err(), expected);
+        let finished = MazeText::parse("1 1 0 2\n0 0\n0 0\n5\n").unwrap();
+        assert!(ch4::MazeState::from_maze_text(&finished).is_ok());
+        assert!(ch5::MazeState::from_maze_text(&finished).is_ok());
+    }
+
+    // 1行や1列の迷路は読み込めて、最後のターンまでプレイできる
+    #[test]
+    fn one_row_and_one_column_mazes_can_be_played() {
+        for text in ["1 3 4 2\n0 0\n0 2\n123\n", "3 1 4 2\n0 0\n2 0\n1\n2\n3\n"] {
+            let maze = MazeText::parse(text).unwrap();
+            assert!(maze.expect_moves().is_ok());
+            let mut state = ch4::MazeState::from_maze_text(&maze).unwrap();
+            while !state.is_done() {
+                state.advance();
+            }
+            let mut state = ch5::MazeState::from_maze_text(&maze).unwrap();
+            let mut rng = new_rng(Some(0));
+            while !state.is_done() {
+                state.advance(random_action(&state, &mut rng));
+            }
+        }
+    }
+
+    #[test]
+    fn saved_maze_loads_back() {
+        let maze = MazeText::parse(MAZE).unwrap();
+        let path = std::env::temp_dir().join(format!("maze_text_test_{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        maze.save(path).unwrap();
+        assert_eq!(MazeText::load(path).unwrap(), maze);
+        std::fs::write(path, "2 3 4\n").unwrap();
+        assert_eq!(
+            MazeText::load(path).unwrap_err(),
+            format!(
+                "{}: line 1: expected 4 numbers (height, width, turns, number of characters) but found 3: 2 3 4",
+                path
+            )
+        );
+        std::fs::remove_file(path).unwrap();
+        assert!(MazeText::load(path).unwrap_err().starts_with(&format!("cannot read {}: ", path)));
+    }
+
+    #[test]
+    fn ch3_state_round_trip() {
+        let config = ch3::MazeConfig { height: 4, width: 5, end_turn: 8, max_point: 9 };
+        for seed in 0..20 {
+            let mut state = ch3::MazeState::new(Some(seed), config);
+            let loaded = ch3::MazeState::from_maze_text(&round_trip(&state.to_maze_text())).unwrap();
+            assert_eq!(loaded, state);
+            // 途中の状態は得点を0に戻し、残りのターン数の問題として読み込まれる
+            let mut rng = new_rng(Some(seed));
+            for _ in 0..3 {
+                state.advance(random_action(&state, &mut rng));
+            }
+            let loaded = ch3::MazeState::from_maze_text(&round_trip(&state.to_maze_text())).unwrap();
+            assert_eq!((loaded.turn, loaded.game_score, loaded.config.end_turn), (0, 0, 5));
+            assert_eq!((&loaded.grid, loaded.character), (&state.grid, state.character));
+            assert_eq!(loaded.zobrist_hash(), state.zobrist_hash());
+            assert_eq!(loaded.to_maze_text(), state.to_maze_text());
+        }
+    }
+
+    #[test]
+    fn ch4_state_round_trip() {
+        for seed in 0..20 {
+            let mut state = ch4::MazeState::with_size(Some(seed), 4, 5, 6, 3);
+            state.init(&mut new_rng(Some(seed)));
+            let loaded = ch4::MazeState::from_maze_text(&round_trip(&state.to_maze_text())).unwrap();
+            assert_eq!(loaded, state);
+            let score = state.get_score(false);
+            // get_scoreと同じく初期位置の点数を0にしてから進める
+            for character in state.characters.clone() {
+                state.grid[character.y][character.x] = 0;
+            }
+            for _ in 0..2 {
+                state.advance();
+            }
+            let loaded = ch4::MazeState::from_maze_text(&round_trip(&state.to_maze_text())).unwrap();
+            assert_eq!((loaded.turn, loaded.game_score, loaded.end_turn), (0, 0, 4));
+            assert_eq!((&loaded.grid, &loaded.characters), (&state.grid, &state.characters));
+            assert_eq!(loaded.get_score(false), score - state.game_score);
+        }
+    }
+
+    #[test]
+    fn ch5_state_round_trip() {
+        for seed in 0..20 {
+            let mut state = ch5::MazeState::with_size(Some(seed), 4, 5, 8);
+            let loaded = ch5::MazeState::from_maze_text(&round_trip(&state.to_maze_text())).unwrap();
+            assert_eq!(loaded.to_maze_text(), state.to_maze_text());
+            assert_eq!(loaded.zobrist_hash(), state.zobrist_hash());
+            // 手番のプレイヤーが1人目として書き出される
+            let mut rng = new_rng(Some(seed));
+            for turn in 0..3 {
+                state.advance(random_action(&state, &mut rng));
+                let loaded = ch5::MazeState::from_maze_text(&round_trip(&state.to_maze_text())).unwrap();
+                assert_eq!((loaded.turn, loaded.end_turn), (0, 7 - turn));
+                assert_eq!(loaded.to_maze_text(), state.to_maze_text());
+                assert_eq!(loaded.legal_actions(), state.legal_actions());
+                assert_eq!(loaded.zobrist_hash(), loaded.compute_hash());
+            }
+        }
+    }
+}
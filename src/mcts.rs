@@ -0,0 +1,315 @@
+// 2人交互ゲームのための原始モンテカルロ法とモンテカルロ木探索(MCTS)
+
+use rand::Rng;
+
+use crate::random::{split_rng, RandomGenerator};
+use crate::search::{random_action, NO_ACTION};
+use crate::{AlternateGameState, TimeKeeper};
+
+// ランダムに終局までプレイして、開始時点の手番のプレイヤーから見た勝率を返す
+pub fn playout<S: AlternateGameState, R: Rng>(state: &mut S, rng: &mut R) -> f64 {
+    let is_first = state.is_first();
+    while !state.is_done() {
+        state.advance(random_action(state, rng));
+    }
+    let win_rate = state.get_first_player_score_fow_win_rate();
+    if is_first {
+        win_rate
+    } else {
+        1.0 - win_rate
+    }
+}
+
+// 合法手ごとにplayout_number回を均等に割り振ってプレイアウトし、平均勝率が最も高い行動を返す
+// playout_numberが合法手の数より少なくても、各合法手を少なくとも1回はプレイアウトする
+pub fn primitive_monte_carlo_action<S: AlternateGameState, R: Rng>(
+    state: &S,
+    playout_number: usize,
+    rng: &mut R,
+) -> usize {
+    let legal_actions = state.legal_actions();
+    let mut values = vec![0.0; legal_actions.len()];
+    let mut counts = vec![0; legal_actions.len()];
+    for cnt in 0..playout_number.max(legal_actions.len()) {
+        let index = cnt % legal_actions.len();
+        let mut next_state = state.clone();
+        next_state.advance(legal_actions[index]);
+        values[index] += 1.0 - playout(&mut next_state, rng);
+        counts[index] += 1;
+    }
+    let mut best_action = NO_ACTION;
+    let mut best_score = -1.0;
+    for (index, &action) in legal_actions.iter().enumerate() {
+        let value_mean = values[index] / counts[index] as f64;
+        if value_mean > best_score {
+            best_score = value_mean;
+            best_action = action;
+        }
+    }
+    best_action
+}
+
+// 探索木の葉ノードの価値の求め方と子ノードの選び方。MCTSとThunder探索はこれだけが違う
+pub trait NodePolicy<S> {
+    // 葉ノードの手番のプレイヤーから見た価値
+    fn leaf_value(&mut self, state: &S) -> f64;
+    // 葉ノードをこの回数だけ訪問したら展開する
+    fn expand_threshold(&self) -> usize;
+    // 訪問済みの子ノードを選ぶときの値で、最大の子ノードを選ぶ
+    // w, nは子ノードの累計価値と訪問回数、tは全ての子ノードの訪問回数の合計
+    fn selection_value(&self, w: f64, n: usize, t: usize) -> f64;
+}
+
+// MCTSの方策。葉ノードはプレイアウトで評価し、UCB1が最大の子ノードを選ぶ
+pub struct Ucb1<'a, R> {
+    pub c: f64,
+    pub expand_threshold: usize,
+    pub rng: &'a mut R,
+}
+impl<S: AlternateGameState, R: Rng> NodePolicy<S> for Ucb1<'_, R> {
+    fn leaf_value(&mut self, state: &S) -> f64 {
+        playout(&mut state.clone(), self.rng)
+    }
+
+    fn expand_threshold(&self) -> usize {
+        self.expand_threshold
+    }
+
+    fn selection_value(&self, w: f64, n: usize, t: usize) -> f64 {
+        1.0 - w / n as f64 + self.c * (2.0 * (t as f64).ln() / n as f64).sqrt()
+    }
+}
+
+// MCTSとThunder探索の探索木のノード。wは現在の手番のプレイヤーから見た累計価値、nは訪問回数
+#[derive(Clone, Debug)]
+pub struct Node<S> {
+    pub state: S,
+    pub w: f64,
+    pub n: usize,
+    pub action: usize,
+    pub child_nodes: Vec<Node<S>>,
+}
+impl<S: AlternateGameState> Node<S> {
+    pub fn new(state: S, action: usize) -> Self {
+        Node {
+            state,
+            w: 0.0,
+            n: 0,
+            action,
+            child_nodes: Vec::new(),
+        }
+    }
+
+    // 選択、展開、葉ノードの評価、逆伝播を1回行い、このノードの手番から見た価値を返す
+    pub fn evaluate<P: NodePolicy<S>>(&mut self, policy: &mut P) -> f64 {
+        if self.state.is_done() {
+            let value = self.state.get_win_rate_of_player_to_move();
+            self.w += value;
+            self.n += 1;
+            return value;
+        }
+        if self.child_nodes.is_empty() {
+            let value = policy.leaf_value(&self.state);
+            self.w += value;
+            self.n += 1;
+            if self.n == policy.expand_threshold() {
+                self.expand();
+            }
+            value
+        } else {
+            let value = 1.0 - self.next_child_node(policy).evaluate(policy);
+            self.w += value;
+            self.n += 1;
+            value
+        }
+    }
+
+    pub fn expand(&mut self) {
+        self.child_nodes = self
+            .state
+            .legal_actions()
+            .into_iter()
+            .map(|action| {
+                let mut next_state = self.state.clone();
+                next_state.advance(action);
+                Node::new(next_state, action)
+            })
+            .collect();
+    }
+
+    // 未訪問の子ノードを優先し、それ以外はpolicyの選択値が最大の子ノードを選ぶ
+    fn next_child_node<P: NodePolicy<S>>(&mut self, policy: &P) -> &mut Node<S> {
+        if let Some(index) = self.child_nodes.iter().position(|child| child.n == 0) {
+            return &mut self.child_nodes[index];
+        }
+        let t: usize = self.child_nodes.iter().map(|child| child.n).sum();
+        let mut best_value = f64::NEG_INFINITY;
+        let mut best_index = 0;
+        for (index, child) in self.child_nodes.iter().enumerate() {
+            let value = policy.selection_value(child.w, child.n, t);
+            if value > best_value {
+                best_value = value;
+                best_index = index;
+            }
+        }
+        &mut self.child_nodes[best_index]
+    }
+
+    // 最も訪問回数が多い子ノードの行動
+    pub fn best_action(&self) -> usize {
+        let mut best_action = NO_ACTION;
+        let mut best_n = 0;
+        for child in self.child_nodes.iter() {
+            if best_action == NO_ACTION || child.n > best_n {
+                best_n = child.n;
+                best_action = child.action;
+            }
+        }
+        best_action
+    }
+}
+
+// プレイアウト回数を指定してMCTSで行動を決定する
+pub fn mcts_action<S: AlternateGameState, R: Rng>(
+    state: &S,
+    playout_number: usize,
+    c: f64,
+    expand_threshold: usize,
+    rng: &mut R,
+) -> usize {
+    let mut root_node = Node::new(state.clone(), NO_ACTION);
+    root_node.expand();
+    let mut policy = Ucb1 { c, expand_threshold, rng };
+    for _ in 0..playout_number {
+        root_node.evaluate(&mut policy);
+    }
+    root_node.best_action()
+}
+
+// 制限時間(秒)まで探索を繰り返してMCTSで行動を決定する
+pub fn mcts_action_with_time_threshold<S: AlternateGameState, R: Rng>(
+    state: &S,
+    time_threshold: f64,
+    c: f64,
+    expand_threshold: usize,
+    rng: &mut R,
+) -> usize {
+    let mut root_node = Node::new(state.clone(), NO_ACTION);
+    root_node.expand();
+    let mut policy = Ucb1 { c, expand_threshold, rng };
+    let time_keeper = TimeKeeper::new(time_threshold);
+    loop {
+        if time_keeper.isTimeOver() {
+            break;
+        }
+        root_node.evaluate(&mut policy);
+    }
+    root_node.best_action()
+}
+
+// thread_number本のスレッドでそれぞれ独立に探索木を作り、根の子ノードの訪問回数を行動ごとに合計して
+// 最も訪問回数が多い行動を返す。searchは根ノードとそのスレッドの乱数生成器を受け取って探索する
+// 各スレッドの乱数生成器はrngから順に作るので、プレイアウト回数で打ち切る探索ならrngが同じとき結果も同じになる
+fn root_parallel_action<S, R, F>(state: &S, thread_number: usize, rng: &mut R, search: F) -> usize
+where
+    S: AlternateGameState + Send + Sync,
+    R: Rng,
+    F: Fn(&mut Node<S>, &mut RandomGenerator) + Sync,
+{
+    let thread_rngs: Vec<RandomGenerator> = (0..thread_number).map(|_| split_rng(rng)).collect();
+    let visit_counts: Vec<Vec<(usize, usize)>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = thread_rngs
+            .into_iter()
+            .map(|mut rng| {
+                let search = &search;
+                scope.spawn(move || {
+                    let mut root_node = Node::new(state.clone(), NO_ACTION);
+                    root_node.expand();
+                    search(&mut root_node, &mut rng);
+                    root_node
+                        .child_nodes
+                        .iter()
+                        .map(|child| (child.action, child.n))
+                        .collect()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+
+    // どのスレッドでも根の子ノードは合法手の順に並んでいる
+    let mut total_counts: Vec<(usize, usize)> = state.legal_actions().into_iter().map(|action| (action, 0)).collect();
+    for counts in visit_counts.iter() {
+        for (index, &(action, n)) in counts.iter().enumerate() {
+            assert_eq!(total_counts[index].0, action);
+            total_counts[index].1 += n;
+        }
+    }
+    let mut best_action = NO_ACTION;
+    let mut best_n = 0;
+    for &(action, n) in total_counts.iter() {
+        if best_action == NO_ACTION || n > best_n {
+            best_n = n;
+            best_action = action;
+        }
+    }
+    best_action
+}
+
+// スレッドごとのプレイアウト回数を指定して、ルート並列化したMCTSで行動を決定する
+pub fn root_parallel_mcts_action<S: AlternateGameState + Send + Sync, R: Rng>(
+    state: &S,
+    playout_number: usize,
+    c: f64,
+    expand_threshold: usize,
+    thread_number: usize,
+    rng: &mut R,
+) -> usize {
+    root_parallel_action(state, thread_number, rng, |root_node, rng| {
+        let mut policy = Ucb1 { c, expand_threshold, rng };
+        for _ in 0..playout_number {
+            root_node.evaluate(&mut policy);
+        }
+    })
+}
+
+// 制限時間(秒)まで各スレッドで探索を繰り返して、ルート並列化したMCTSで行動を決定する
+pub fn root_parallel_mcts_action_with_time_threshold<S: AlternateGameState + Send + Sync, R: Rng>(
+    state: &S,
+    time_threshold: f64,
+    c: f64,
+    expand_threshold: usize,
+    thread_number: usize,
+    rng: &mut R,
+) -> usize {
+    let time_keeper = TimeKeeper::new(time_threshold);
+    root_parallel_action(state, thread_number, rng, |root_node, rng| {
+        let mut policy = Ucb1 { c, expand_threshold, rng };
+        loop {
+            if time_keeper.isTimeOver() {
+                break;
+            }
+            root_node.evaluate(&mut policy);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_text::MazeText;
+    use crate::{ch5, new_rng, GameState};
+
+    // 残り1ターンで、上(最後の合法手)に動いたときだけ点数を取って勝てる迷路
+    const LAST_TURN_MAZE: &str = "3 3 1 2\n1 1\n0 0\n090\n000\n000\n";
+
+    #[test]
+    fn primitive_monte_carlo_tries_every_action_with_few_playouts() {
+        let state = ch5::MazeState::from_maze_text(&MazeText::parse(LAST_TURN_MAZE).unwrap()).unwrap();
+        assert_eq!(state.legal_actions(), vec![0, 1, 2, 3]);
+        let mut rng = new_rng(Some(0));
+        for playout_number in [0, 1, 2, 4, 10] {
+            assert_eq!(primitive_monte_carlo_action(&state, playout_number, &mut rng), 3, "{}", playout_number);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// AIや局所探索が使う乱数生成器
+// seedを指定すれば実験全体を同じ乱数列で再現でき、指定しなければエントロピーから初期化する
+
+use rand::SeedableRng;
+
+// プラットフォームによらず同じ乱数列になるPCG
+pub type RandomGenerator = rand_pcg::Pcg64Mcg;
+
+pub fn new_rng(seed: Option<u64>) -> RandomGenerator {
+    match seed {
+        Some(seed) => RandomGenerator::seed_from_u64(seed),
+        None => RandomGenerator::from_entropy(),
+    }
+}
+
+// 親の乱数生成器から子の乱数生成器を作る。並列に探索するときにスレッドごとに分ける
+pub fn split_rng<R: rand::Rng>(rng: &mut R) -> RandomGenerator {
+    RandomGenerator::seed_from_u64(rng.gen())
+}
+
+// 実験のgame番目のゲームのseed。seedが大きくてもオーバーフローしないように折り返す
+pub fn game_seed(seed: Option<u64>, game: usize) -> Option<u64> {
+    seed.map(|seed| seed.wrapping_add(game as u64))
+}
+
+// 実験のgame番目のゲームでAIが使う乱数生成器
+// ゲームごとに独立に作るので、並列に実行しても並び順によらず同じ乱数列になる
+pub fn game_rng(seed: Option<u64>, game: usize) -> RandomGenerator {
+    new_rng(game_seed(seed, game))
+}
@@ -0,0 +1,480 @@
+// GameStateに対して汎用に使える探索アルゴリズム
+
+use rand::Rng;
+use std::cell::Cell;
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::{GameState, HashedGameState, ScoreType, TimeKeeper};
+
+// 行動が一つも選ばれていないことを表す値
+pub const NO_ACTION: usize = usize::MAX;
+
+// ビームに積む状態。評価値と最初の行動を状態と一緒に持つ
+#[derive(Clone, Debug)]
+struct BeamNode<S> {
+    state: S,
+    evaluate_score: ScoreType,
+    first_action: usize,
+}
+impl<S: GameState> BeamNode<S> {
+    fn root(state: &S) -> Self {
+        BeamNode {
+            state: state.clone(),
+            evaluate_score: state.evaluate(),
+            first_action: NO_ACTION,
+        }
+    }
+
+    fn child(&self, action: usize, is_first: bool) -> Self {
+        let mut state = self.state.clone();
+        state.advance(action);
+        BeamNode {
+            evaluate_score: state.evaluate(),
+            state,
+            first_action: if is_first { action } else { self.first_action },
+        }
+    }
+}
+impl<S> std::cmp::PartialEq for BeamNode<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.evaluate_score == other.evaluate_score
+    }
+}
+impl<S> std::cmp::Eq for BeamNode<S> {}
+impl<S> std::cmp::PartialOrd for BeamNode<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<S> std::cmp::Ord for BeamNode<S> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.evaluate_score.cmp(&other.evaluate_score)
+    }
+}
+
+pub fn random_action<S: GameState, R: Rng>(state: &S, rng: &mut R) -> usize {
+    let legal_actions: Vec<usize> = state.legal_actions();
+    let index = rng.gen_range(0, legal_actions.len());
+    legal_actions[index]
+}
+
+// 1手先の評価値が最も高い行動。同点なら先に見つけたほう
+pub fn greedy_action<S: GameState>(state: &S) -> usize {
+    let mut max_score = ScoreType::MIN;
+    let mut best_action = NO_ACTION;
+    for action in state.legal_actions() {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        let score = next_state.evaluate();
+        if score > max_score {
+            max_score = score;
+            best_action = action;
+        }
+    }
+    best_action
+}
+
+// 局面のハッシュを返す関数。指定されていれば同じハッシュの状態は最も評価値の高いものだけを展開する
+type HashFunction<S> = Option<fn(&S) -> u64>;
+
+// now_beamから上位beam_width個を展開して次の深さのビームを作る。捨てた重複状態の数をdroppedに足す
+fn beam_search_step<S: GameState>(
+    mut now_beam: BinaryHeap<BeamNode<S>>,
+    beam_width: usize,
+    is_first: bool,
+    hash: HashFunction<S>,
+    dropped: &mut usize,
+) -> BinaryHeap<BeamNode<S>> {
+    let mut next_beam: BinaryHeap<BeamNode<S>> = BinaryHeap::new();
+    let mut seen: HashSet<u64> = HashSet::new();
+    let mut w = 0;
+    while w < beam_width {
+        let Some(tmp_node) = now_beam.pop() else {
+            break;
+        };
+        if let Some(hash) = hash {
+            if !seen.insert(hash(&tmp_node.state)) {
+                *dropped += 1;
+                continue;
+            }
+        }
+        for action in tmp_node.state.legal_actions() {
+            next_beam.push(tmp_node.child(action, is_first));
+        }
+        w += 1;
+    }
+    next_beam
+}
+
+fn beam_search<S: GameState>(state: &S, beam_width: usize, beam_depth: usize, hash: HashFunction<S>) -> (usize, usize) {
+    let mut dropped = 0;
+    let mut now_beam: BinaryHeap<BeamNode<S>> = BinaryHeap::new();
+    now_beam.push(BeamNode::root(state));
+    for t in 0..beam_depth {
+        now_beam = beam_search_step(now_beam, beam_width, t == 0, hash, &mut dropped);
+        let best_node = now_beam.peek().unwrap();
+        if best_node.state.is_done() {
+            break;
+        }
+    }
+    (now_beam.peek().unwrap().first_action, dropped)
+}
+
+fn beam_search_with_time_threshold<S: GameState>(
+    state: &S,
+    beam_width: usize,
+    time_threshold: f64,
+    hash: HashFunction<S>,
+) -> (usize, usize) {
+    let mut dropped = 0;
+    let mut now_beam: BinaryHeap<BeamNode<S>> = BinaryHeap::new();
+    now_beam.push(BeamNode::root(state));
+    let time_keeper = TimeKeeper::new(time_threshold);
+
+    for t in 0.. {
+        now_beam = beam_search_step(now_beam, beam_width, t == 0, hash, &mut dropped);
+        let best_node = now_beam.peek().unwrap();
+        if best_node.state.is_done() || time_keeper.isTimeOver() {
+            break;
+        }
+    }
+    (now_beam.peek().unwrap().first_action, dropped)
+}
+
+pub fn beam_search_action<S: GameState>(state: &S, beam_width: usize, beam_depth: usize) -> usize {
+    beam_search(state, beam_width, beam_depth, None).0
+}
+
+pub fn beam_search_action_with_time_threshold<S: GameState>(
+    state: &S,
+    beam_width: usize,
+    time_threshold: f64,
+) -> usize {
+    beam_search_with_time_threshold(state, beam_width, time_threshold, None).0
+}
+
+thread_local! {
+    // このスレッドで重複を除いた探索が捨てた状態の数の累計
+    static DROPPED: Cell<usize> = const { Cell::new(0) };
+}
+
+// このスレッドで重複を除いた探索が捨てた状態の数の累計を返してリセットする
+pub fn take_dropped() -> usize {
+    DROPPED.with(|dropped| dropped.replace(0))
+}
+
+fn record_dropped((action, dropped): (usize, usize)) -> (usize, usize) {
+    DROPPED.with(|total| total.set(total.get() + dropped));
+    (action, dropped)
+}
+
+// 同じハッシュの状態を1つにまとめるビームサーチ。行動と捨てた重複状態の数を返す
+// 捨てた状態の数はtake_droppedで集計できるように記録する
+pub fn beam_search_action_dedup<S: HashedGameState>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+) -> (usize, usize) {
+    record_dropped(beam_search(state, beam_width, beam_depth, Some(S::zobrist_hash)))
+}
+
+pub fn beam_search_action_with_time_threshold_dedup<S: HashedGameState>(
+    state: &S,
+    beam_width: usize,
+    time_threshold: f64,
+) -> (usize, usize) {
+    record_dropped(beam_search_with_time_threshold(state, beam_width, time_threshold, Some(S::zobrist_hash)))
+}
+
+// beamの各深さから上位beam_width個ずつ展開する処理を1回分行う
+// hashが指定されていれば、各深さで既に展開したハッシュの状態は捨ててdroppedに数える
+fn chokudai_search_step<S: GameState>(
+    beam: &mut [BinaryHeap<BeamNode<S>>],
+    beam_width: usize,
+    hash: HashFunction<S>,
+    expanded: &mut [HashSet<u64>],
+    dropped: &mut usize,
+) {
+    let beam_depth = beam.len() - 1;
+    for t in 0..beam_depth {
+        let mut w = 0;
+        while w < beam_width {
+            let Some(now_node) = beam[t].peek() else {
+                break;
+            };
+            if now_node.state.is_done() {
+                break;
+            }
+            let now_node = beam[t].pop().unwrap();
+            if let Some(hash) = hash {
+                if !expanded[t].insert(hash(&now_node.state)) {
+                    *dropped += 1;
+                    continue;
+                }
+            }
+            for action in now_node.state.legal_actions() {
+                let next_node = now_node.child(action, t == 0);
+                beam[t + 1].push(next_node);
+            }
+            w += 1;
+        }
+    }
+}
+
+fn chokudai_best_action<S>(beam: &[BinaryHeap<BeamNode<S>>]) -> usize {
+    for now_beam in beam.iter().rev() {
+        if let Some(best_node) = now_beam.peek() {
+            return best_node.first_action;
+        }
+    }
+    NO_ACTION
+}
+
+fn chokudai_search<S: GameState>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    beam_number: usize,
+    hash: HashFunction<S>,
+) -> (usize, usize) {
+    let mut dropped = 0;
+    let mut expanded: Vec<HashSet<u64>> = vec![HashSet::new(); beam_depth];
+    let mut beam: Vec<BinaryHeap<BeamNode<S>>> = vec![BinaryHeap::new(); beam_depth + 1];
+    beam[0].push(BeamNode::root(state));
+    for _cnt in 0..beam_number {
+        chokudai_search_step(&mut beam, beam_width, hash, &mut expanded, &mut dropped);
+    }
+    (chokudai_best_action(&beam), dropped)
+}
+
+fn chokudai_search_with_time_threshold<S: GameState>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    time_threshold: f64,
+    hash: HashFunction<S>,
+) -> (usize, usize) {
+    let mut dropped = 0;
+    let mut expanded: Vec<HashSet<u64>> = vec![HashSet::new(); beam_depth];
+    let mut beam: Vec<BinaryHeap<BeamNode<S>>> = vec![BinaryHeap::new(); beam_depth + 1];
+    beam[0].push(BeamNode::root(state));
+    let time_keeper = TimeKeeper::new(time_threshold);
+    loop {
+        chokudai_search_step(&mut beam, beam_width, hash, &mut expanded, &mut dropped);
+        if time_keeper.isTimeOver() {
+            break;
+        }
+    }
+    (chokudai_best_action(&beam), dropped)
+}
+
+pub fn chokudai_search_action<S: GameState>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    beam_number: usize,
+) -> usize {
+    chokudai_search(state, beam_width, beam_depth, beam_number, None).0
+}
+
+pub fn chokudai_search_action_with_time_threshold<S: GameState>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    time_threshold: f64,
+) -> usize {
+    chokudai_search_with_time_threshold(state, beam_width, beam_depth, time_threshold, None).0
+}
+
+// 同じハッシュの状態を1つにまとめるchokudaiサーチ。行動と捨てた重複状態の数を返し、捨てた数を記録する
+pub fn chokudai_search_action_dedup<S: HashedGameState>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    beam_number: usize,
+) -> (usize, usize) {
+    record_dropped(chokudai_search(state, beam_width, beam_depth, beam_number, Some(S::zobrist_hash)))
+}
+
+pub fn chokudai_search_action_with_time_threshold_dedup<S: HashedGameState>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    time_threshold: f64,
+) -> (usize, usize) {
+    let hash: HashFunction<S> = Some(S::zobrist_hash);
+    record_dropped(chokudai_search_with_time_threshold(state, beam_width, beam_depth, time_threshold, hash))
+}
+
+// minimaxのためのスコア計算
+pub fn mini_max_score<S: GameState>(state: &S, depth: usize) -> ScoreType {
+    if state.is_done() || depth == 0 {
+        return state.evaluate();
+    }
+    let legal_actions = state.legal_actions();
+    if legal_actions.is_empty() {
+        return state.evaluate();
+    }
+    let mut best_score = -1_000_000;
+    for action in legal_actions {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        let score = -mini_max_score(&next_state, depth - 1);
+        if score > best_score {
+            best_score = score;
+        }
+    }
+    best_score
+}
+
+// 深さを指定してminimaxで行動を決定する
+pub fn mini_max_action<S: GameState>(state: &S, depth: usize) -> usize {
+    let mut best_action = NO_ACTION;
+    let mut best_score = -1_000_000;
+    for action in state.legal_actions() {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        let score = -mini_max_score(&next_state, depth);
+        if score > best_score {
+            best_action = action;
+            best_score = score;
+        }
+    }
+    best_action
+}
+
+pub fn alpha_beta_score<S: GameState>(state: &S, mut alpha: ScoreType, beta: ScoreType, depth: usize) -> ScoreType {
+    if state.is_done() || depth == 0 {
+        return state.evaluate();
+    }
+    let legal_actions = state.legal_actions();
+    if legal_actions.is_empty() {
+        return state.evaluate();
+    }
+    for action in legal_actions {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        let score = -alpha_beta_score(&next_state, -beta, -alpha, depth - 1);
+        if score > alpha {
+            alpha = score;
+        }
+        if alpha >= beta {
+            return alpha;
+        }
+    }
+    alpha
+}
+
+// 深さを指定してalpha-betaで行動を決定する
+pub fn alpha_beta_action<S: GameState>(state: &S, depth: usize) -> usize {
+    let mut best_action = NO_ACTION;
+    let mut alpha = -1_000_000;
+    let beta = 1_000_000;
+    for action in state.legal_actions() {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        let score = -alpha_beta_score(&next_state, -beta, -alpha, depth);
+        if score > alpha {
+            alpha = score;
+            best_action = action;
+        }
+    }
+    best_action
+}
+
+// 制限時間を超えた場合はNoneを返して探索を打ち切る
+// 深さ制限で打ち切ったノードがあればis_cutoffをtrueにする
+fn alpha_beta_score_with_time_keeper<S: GameState>(
+    state: &S,
+    mut alpha: ScoreType,
+    beta: ScoreType,
+    depth: usize,
+    time_keeper: &TimeKeeper,
+    is_cutoff: &mut bool,
+) -> Option<ScoreType> {
+    if time_keeper.isTimeOver() {
+        return None;
+    }
+    if state.is_done() {
+        return Some(state.evaluate());
+    }
+    if depth == 0 {
+        *is_cutoff = true;
+        return Some(state.evaluate());
+    }
+    let legal_actions = state.legal_actions();
+    if legal_actions.is_empty() {
+        return Some(state.evaluate());
+    }
+    for action in legal_actions {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        let score = -alpha_beta_score_with_time_keeper(&next_state, -beta, -alpha, depth - 1, time_keeper, is_cutoff)?;
+        if score > alpha {
+            alpha = score;
+        }
+        if alpha >= beta {
+            return Some(alpha);
+        }
+    }
+    Some(alpha)
+}
+
+fn alpha_beta_action_with_time_keeper<S: GameState>(
+    state: &S,
+    depth: usize,
+    time_keeper: &TimeKeeper,
+    is_cutoff: &mut bool,
+) -> Option<usize> {
+    let mut best_action = NO_ACTION;
+    let mut alpha = -1_000_000;
+    let beta = 1_000_000;
+    for action in state.legal_actions() {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        let score = -alpha_beta_score_with_time_keeper(&next_state, -beta, -alpha, depth, time_keeper, is_cutoff)?;
+        if score > alpha {
+            alpha = score;
+            best_action = action;
+        }
+    }
+    Some(best_action)
+}
+
+thread_local! {
+    // このスレッドで反復深化が探索しきった深さの合計、呼び出し回数、最大値
+    static REACHED_DEPTHS: Cell<(usize, usize, usize)> = const { Cell::new((0, 0, 0)) };
+}
+
+// このスレッドで反復深化が探索しきった深さの合計、呼び出し回数、最大値を返してリセットする
+pub fn take_reached_depths() -> (usize, usize, usize) {
+    REACHED_DEPTHS.with(|depths| depths.replace((0, 0, 0)))
+}
+
+// 制限時間(秒)まで深さを1ずつ増やしながらalpha-betaを繰り返す
+// 最後に探索しきった深さでの最善手と、その深さを返す(1つも探索しきれなければ深さ0で最初の合法手)
+// 探索しきった深さはtake_reached_depthsで集計できるように記録する
+pub fn iterative_deepening_action<S: GameState>(state: &S, time_threshold: f64) -> (usize, usize) {
+    let time_keeper = TimeKeeper::new(time_threshold);
+    let mut best_action = state.legal_actions().first().copied().unwrap_or(NO_ACTION);
+    let mut reached_depth = 0;
+    for depth in 1.. {
+        let mut is_cutoff = false;
+        match alpha_beta_action_with_time_keeper(state, depth, &time_keeper, &mut is_cutoff) {
+            Some(action) => {
+                best_action = action;
+                reached_depth = depth;
+            }
+            None => break,
+        }
+        // 深さ制限で打ち切ったノードがなければ、これ以上深くしても結果は変わらない
+        if !is_cutoff {
+            break;
+        }
+    }
+    REACHED_DEPTHS.with(|depths| {
+        let (total, count, max) = depths.get();
+        depths.set((total + reached_depth, count + 1, max.max(reached_depth)));
+    });
+    (best_action, reached_depth)
+}
@@ -0,0 +1,246 @@
+// 2人同時着手ゲームのための探索アルゴリズム
+// 価値はすべて先手から見た勝率で扱う
+
+use rand::Rng;
+
+use crate::search::NO_ACTION;
+use crate::{SimultaneousGameState, TimeKeeper};
+
+pub fn random_action<S: SimultaneousGameState, R: Rng>(state: &S, player_id: usize, rng: &mut R) -> usize {
+    let legal_actions = state.legal_actions(player_id);
+    legal_actions[rng.gen_range(0, legal_actions.len())]
+}
+
+// 両者ランダムに終局までプレイして先手から見た勝率を返す
+pub fn playout<S: SimultaneousGameState, R: Rng>(state: &mut S, rng: &mut R) -> f64 {
+    while !state.is_done() {
+        let action0 = random_action(state, 0, rng);
+        let action1 = random_action(state, 1, rng);
+        state.advance(action0, action1);
+    }
+    state.get_first_player_score_fow_win_rate()
+}
+
+// player_idの合法手ごとにplayout_number回を均等に割り振り、相手はランダムに行動するとしてプレイアウトする
+// playout_numberが合法手の数より少なくても、各合法手を少なくとも1回はプレイアウトする
+pub fn primitive_monte_carlo_action<S: SimultaneousGameState, R: Rng>(
+    state: &S,
+    player_id: usize,
+    playout_number: usize,
+    rng: &mut R,
+) -> usize {
+    let legal_actions = state.legal_actions(player_id);
+    let mut values = vec![0.0; legal_actions.len()];
+    let mut counts = vec![0; legal_actions.len()];
+    for cnt in 0..playout_number.max(legal_actions.len()) {
+        let index = cnt % legal_actions.len();
+        let mut next_state = state.clone();
+        if player_id == 0 {
+            next_state.advance(legal_actions[index], random_action(state, 1, rng));
+        } else {
+            next_state.advance(random_action(state, 0, rng), legal_actions[index]);
+        }
+        let win_rate = playout(&mut next_state, rng);
+        values[index] += if player_id == 0 { win_rate } else { 1.0 - win_rate };
+        counts[index] += 1;
+    }
+    let mut best_action = NO_ACTION;
+    let mut best_score = -1.0;
+    for (index, &action) in legal_actions.iter().enumerate() {
+        let value_mean = values[index] / counts[index] as f64;
+        if value_mean > best_score {
+            best_score = value_mean;
+            best_action = action;
+        }
+    }
+    best_action
+}
+
+// DUCT(Decoupled UCT)の探索木のノード
+// child_nodeses[i][j]は先手がactions[0][i]、後手がactions[1][j]を選んだ後のノード
+#[derive(Clone, Debug)]
+pub struct Node<S> {
+    pub state: S,
+    pub w: f64,
+    pub n: usize,
+    pub actions: [Vec<usize>; 2],
+    pub child_nodeses: Vec<Vec<Node<S>>>,
+}
+impl<S: SimultaneousGameState> Node<S> {
+    pub fn new(state: S) -> Self {
+        Node {
+            state,
+            w: 0.0,
+            n: 0,
+            actions: [Vec::new(), Vec::new()],
+            child_nodeses: Vec::new(),
+        }
+    }
+
+    // 選択、展開、プレイアウト、逆伝播を1回行い、先手から見た価値を返す
+    pub fn evaluate<R: Rng>(&mut self, c: f64, expand_threshold: usize, rng: &mut R) -> f64 {
+        if self.state.is_done() {
+            let value = self.state.get_first_player_score_fow_win_rate();
+            self.w += value;
+            self.n += 1;
+            return value;
+        }
+        if self.child_nodeses.is_empty() {
+            let value = playout(&mut self.state.clone(), rng);
+            self.w += value;
+            self.n += 1;
+            if self.n == expand_threshold {
+                self.expand();
+            }
+            value
+        } else {
+            let value = self.next_child_node(c).evaluate(c, expand_threshold, rng);
+            self.w += value;
+            self.n += 1;
+            value
+        }
+    }
+
+    pub fn expand(&mut self) {
+        self.actions = [self.state.legal_actions(0), self.state.legal_actions(1)];
+        self.child_nodeses = self.actions[0]
+            .iter()
+            .map(|&action0| {
+                self.actions[1]
+                    .iter()
+                    .map(|&action1| {
+                        let mut next_state = self.state.clone();
+                        next_state.advance(action0, action1);
+                        Node::new(next_state)
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+
+    // player_idの行動indexごとの累計価値と訪問回数(相手の行動について合計する)
+    fn action_statistics(&self, player_id: usize) -> Vec<(f64, usize)> {
+        (0..self.actions[player_id].len())
+            .map(|index| {
+                let children: Vec<&Node<S>> = if player_id == 0 {
+                    self.child_nodeses[index].iter().collect()
+                } else {
+                    self.child_nodeses.iter().map(|row| &row[index]).collect()
+                };
+                let w = children.iter().map(|child| child.w).sum();
+                let n = children.iter().map(|child| child.n).sum();
+                (w, n)
+            })
+            .collect()
+    }
+
+    // 未訪問の子ノードを優先し、それ以外は先手と後手がそれぞれ独立にUCB1が最大の行動を選ぶ
+    fn next_child_node(&mut self, c: f64) -> &mut Node<S> {
+        for i in 0..self.child_nodeses.len() {
+            for j in 0..self.child_nodeses[i].len() {
+                if self.child_nodeses[i][j].n == 0 {
+                    return &mut self.child_nodeses[i][j];
+                }
+            }
+        }
+        let t: usize = self.child_nodeses.iter().flatten().map(|child| child.n).sum();
+        let mut best_indexes = [0; 2];
+        for (player_id, best_index) in best_indexes.iter_mut().enumerate() {
+            let mut best_value = f64::NEG_INFINITY;
+            for (index, (w, n)) in self.action_statistics(player_id).into_iter().enumerate() {
+                let win_rate = if player_id == 0 { w / n as f64 } else { 1.0 - w / n as f64 };
+                let ucb1_value = win_rate + c * (2.0 * (t as f64).ln() / n as f64).sqrt();
+                if ucb1_value > best_value {
+                    best_value = ucb1_value;
+                    *best_index = index;
+                }
+            }
+        }
+        &mut self.child_nodeses[best_indexes[0]][best_indexes[1]]
+    }
+
+    // player_idにとって最も訪問回数が多い行動
+    pub fn best_action(&self, player_id: usize) -> usize {
+        let mut best_action = NO_ACTION;
+        let mut best_n = 0;
+        for (index, (_, n)) in self.action_statistics(player_id).into_iter().enumerate() {
+            if best_action == NO_ACTION || n > best_n {
+                best_n = n;
+                best_action = self.actions[player_id][index];
+            }
+        }
+        best_action
+    }
+}
+
+// プレイアウト回数を指定してDUCTでplayer_idの行動を決定する
+pub fn duct_action<S: SimultaneousGameState, R: Rng>(
+    state: &S,
+    player_id: usize,
+    playout_number: usize,
+    c: f64,
+    expand_threshold: usize,
+    rng: &mut R,
+) -> usize {
+    let mut root_node = Node::new(state.clone());
+    root_node.expand();
+    for _ in 0..playout_number {
+        root_node.evaluate(c, expand_threshold, rng);
+    }
+    root_node.best_action(player_id)
+}
+
+// 制限時間(秒)まで探索を繰り返してDUCTでplayer_idの行動を決定する
+pub fn duct_action_with_time_threshold<S: SimultaneousGameState, R: Rng>(
+    state: &S,
+    player_id: usize,
+    time_threshold: f64,
+    c: f64,
+    expand_threshold: usize,
+    rng: &mut R,
+) -> usize {
+    let mut root_node = Node::new(state.clone());
+    root_node.expand();
+    let time_keeper = TimeKeeper::new(time_threshold);
+    loop {
+        if time_keeper.isTimeOver() {
+            break;
+        }
+        root_node.evaluate(c, expand_threshold, rng);
+    }
+    root_node.best_action(player_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ch6, new_rng};
+
+    // 最終ターンで、player_idのキャラクターが上(最後の合法手)に動いたときだけ点数を取って勝てる盤面
+    fn last_turn_state(player_id: usize) -> ch6::MazeState {
+        let mut state = ch6::MazeState::new(Some(0));
+        state.turn = ch6::END_TURN - 1;
+        state.grid = vec![vec![0; ch6::W]; ch6::H];
+        let character = state.characters[player_id];
+        state.grid[character.y - 1][character.x] = 9;
+        state
+    }
+
+    #[test]
+    fn primitive_monte_carlo_tries_every_action_with_few_playouts() {
+        let mut rng = new_rng(Some(0));
+        for player_id in 0..2 {
+            let state = last_turn_state(player_id);
+            assert_eq!(state.legal_actions(player_id), vec![0, 1, 2, 3]);
+            for playout_number in [0, 1, 2, 4, 10] {
+                assert_eq!(
+                    primitive_monte_carlo_action(&state, player_id, playout_number, &mut rng),
+                    3,
+                    "player {} playout {}",
+                    player_id,
+                    playout_number
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,263 @@
+// AIの成績を集計するための統計量
+
+// 標本の平均と標準偏差(不偏分散の平方根)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+    pub n: usize,
+    pub mean: f64,
+    pub std: f64,
+}
+impl Summary {
+    pub fn new(values: &[f64]) -> Self {
+        let n = values.len();
+        if n == 0 {
+            return Summary { n, mean: 0.0, std: 0.0 };
+        }
+        let mean = values.iter().sum::<f64>() / n as f64;
+        let std = if n > 1 {
+            let variance = values.iter().map(|value| (value - mean) * (value - mean)).sum::<f64>() / (n - 1) as f64;
+            variance.sqrt()
+        } else {
+            0.0
+        };
+        Summary { n, mean, std }
+    }
+
+    // 平均の95%信頼区間(正規近似)
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        if self.n == 0 {
+            return (self.mean, self.mean);
+        }
+        let half_width = 1.96 * self.std / (self.n as f64).sqrt();
+        (self.mean - half_width, self.mean + half_width)
+    }
+}
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (lower, upper) = self.confidence_interval();
+        write!(f, "{} (std {:.3}, 95% CI [{:.3}, {:.3}])", self.mean, self.std, lower, upper)
+    }
+}
+
+// 95%信頼区間に対応する標準正規分布の分位点
+const Z_95: f64 = 1.96;
+
+// 勝率(引き分けは0.5勝)をElo ratingの差に変換する
+pub fn elo_difference(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+// Elo ratingの差から期待される勝率
+pub fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+// あるAIから見た対戦成績
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MatchResult {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+impl MatchResult {
+    // 勝ち1.0、引き分け0.5、負け0.0の勝ち点を1ゲーム分加える
+    pub fn add(&mut self, point: f64) {
+        if point > 0.5 {
+            self.wins += 1;
+        } else if point < 0.5 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    // 引き分けを0.5勝とした勝率
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64
+    }
+
+    // 1ゲームあたりの勝ち点の分散
+    pub fn variance(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        let score = self.score();
+        let squared_error = self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2);
+        squared_error / self.games() as f64
+    }
+
+    // 勝率の95%信頼区間(Wilson score interval)
+    pub fn wilson_interval(&self) -> (f64, f64) {
+        let n = self.games() as f64;
+        if n == 0.0 {
+            return (0.0, 1.0);
+        }
+        let score = self.score();
+        let denominator = 1.0 + Z_95 * Z_95 / n;
+        let center = (score + Z_95 * Z_95 / (2.0 * n)) / denominator;
+        let half_width = Z_95 * (score * (1.0 - score) / n + Z_95 * Z_95 / (4.0 * n * n)).sqrt() / denominator;
+        // 全敗や全勝のときの端は丸め誤差を除けばちょうど0.0や1.0になる
+        let lower = if score == 0.0 { 0.0 } else { center - half_width };
+        let upper = if score == 1.0 { 1.0 } else { center + half_width };
+        (lower, upper)
+    }
+
+    // Elo ratingの差の推定値と、勝率の95%信頼区間(Wilson score interval)を変換したその区間
+    // 全勝や全敗のときは推定値と区間の片側が無限大になるが、もう片側は有限に残る
+    pub fn elo(&self) -> (f64, f64, f64) {
+        let (lower, upper) = self.wilson_interval();
+        (elo_difference(self.score()), elo_difference(lower), elo_difference(upper))
+    }
+}
+impl std::fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (lower, upper) = self.wilson_interval();
+        let (elo, elo_lower, elo_upper) = self.elo();
+        write!(
+            f,
+            "W {} D {} L {} ({} games), score {:.3} (95% Wilson CI [{:.3}, {:.3}]), Elo {:.1} [{:.1}, {:.1}]",
+            self.wins,
+            self.draws,
+            self.losses,
+            self.games(),
+            self.score(),
+            lower,
+            upper,
+            elo,
+            elo_lower,
+            elo_upper
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SprtStatus {
+    CONTINUE,
+    // 帰無仮説(Elo ratingの差がelo0)を採択した
+    H0,
+    // 対立仮説(Elo ratingの差がelo1)を採択した
+    H1,
+}
+
+// 逐次確率比検定(SPRT)。勝ち点を正規分布で近似して対数尤度比を計算する
+// alphaは第1種の誤り(H0が正しいのにH1を採択する)、betaは第2種の誤りの確率
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+impl Sprt {
+    // -elo対+eloで検定し、どちらが強いかを有意水準alphaで判定する
+    pub fn symmetric(elo: f64, alpha: f64) -> Self {
+        Sprt {
+            elo0: -elo,
+            elo1: elo,
+            alpha,
+            beta: alpha,
+        }
+    }
+
+    // 対数尤度比の下限と上限。下限を下回ればH0、上限を上回ればH1を採択する
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    // 対数尤度比。勝ち点の分散は標本から求めるが、結果がすべて同じで0になるときは
+    // 2つの仮説の中間の勝率で勝ち負けだけが起きる場合の分散を使う
+    pub fn llr(&self, result: &MatchResult) -> f64 {
+        let (score0, score1) = (expected_score(self.elo0), expected_score(self.elo1));
+        let mut variance = result.variance();
+        if variance <= 0.0 {
+            let score = (score0 + score1) / 2.0;
+            variance = score * (1.0 - score);
+        }
+        let n = result.games() as f64;
+        n * (score1 - score0) * (2.0 * result.score() - score0 - score1) / (2.0 * variance)
+    }
+
+    pub fn status(&self, result: &MatchResult) -> SprtStatus {
+        let llr = self.llr(result);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            SprtStatus::H0
+        } else if llr >= upper {
+            SprtStatus::H1
+        } else {
+            SprtStatus::CONTINUE
+        }
+    }
+}
+
+// 総当たりの対戦成績results[i][j](iから見たjとの成績。results[j][i]はその裏返し)から
+// Elo ratingを推定する(平均が0になるようにする)
+// 引き分けを0.5勝0.5敗としたBradley-Terryモデルの最尤推定をMMアルゴリズムで求める
+// 全勝や全敗でも発散しないよう、BayesEloと同じく各組にprior_draws回の引き分けを仮想的に加える
+pub fn fit_elo_ratings(results: &[Vec<MatchResult>], prior_draws: f64) -> Vec<f64> {
+    let n = results.len();
+    let games = |i: usize, j: usize| results[i][j].games() as f64 + prior_draws;
+    let wins: Vec<f64> = (0..n)
+        .map(|i| {
+            (0..n)
+                .filter(|&j| j != i)
+                .map(|j| results[i][j].wins as f64 + 0.5 * (results[i][j].draws as f64 + prior_draws))
+                .sum()
+        })
+        .collect();
+    let mut gammas = vec![1.0; n];
+    for _ in 0..10000 {
+        let mut max_change: f64 = 0.0;
+        for i in 0..n {
+            let denominator: f64 = (0..n).filter(|&j| j != i).map(|j| games(i, j) / (gammas[i] + gammas[j])).sum();
+            if denominator <= 0.0 {
+                continue;
+            }
+            let gamma = wins[i] / denominator;
+            max_change = max_change.max((gamma / gammas[i]).ln().abs());
+            gammas[i] = gamma;
+        }
+        if max_change < 1e-9 {
+            break;
+        }
+    }
+    let ratings: Vec<f64> = gammas.iter().map(|gamma| 400.0 * gamma.log10()).collect();
+    let mean = ratings.iter().sum::<f64>() / n.max(1) as f64;
+    ratings.iter().map(|rating| rating - mean).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elo_interval_of_one_sided_match_stays_finite_on_one_side() {
+        let losses = MatchResult { wins: 0, draws: 0, losses: 6 };
+        let (elo, lower, upper) = losses.elo();
+        assert_eq!((elo, lower), (f64::NEG_INFINITY, f64::NEG_INFINITY));
+        assert!(upper.is_finite() && upper < 0.0);
+        let wins = MatchResult { wins: 6, draws: 0, losses: 0 };
+        let (elo, lower, upper) = wins.elo();
+        assert_eq!((elo, upper), (f64::INFINITY, f64::INFINITY));
+        assert!(lower.is_finite() && lower > 0.0);
+    }
+
+    #[test]
+    fn elo_interval_matches_wilson_interval() {
+        let result = MatchResult { wins: 20, draws: 1, losses: 19 };
+        let (lower, upper) = result.wilson_interval();
+        let (elo, elo_lower, elo_upper) = result.elo();
+        assert!(elo_lower < elo && elo < elo_upper);
+        assert!((expected_score(elo_lower) - lower).abs() < 1e-9);
+        assert!((expected_score(elo_upper) - upper).abs() < 1e-9);
+    }
+}
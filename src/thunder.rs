@@ -0,0 +1,47 @@
+// 2人交互ゲームのためのThunder探索
+// MCTSのプレイアウトの代わりに盤面の評価値を使い、UCB1の探索項を使わずに子ノードを選ぶ
+// 探索木はMCTSと同じNodeを使う
+
+use crate::mcts::{Node, NodePolicy};
+use crate::search::NO_ACTION;
+use crate::{AlternateGameState, TimeKeeper};
+
+// Thunder探索の方策。葉ノードは評価値を価値として即座に展開し、自分から見た勝率が最大の子ノードを選ぶ
+pub struct Thunder;
+impl<S: AlternateGameState> NodePolicy<S> for Thunder {
+    fn leaf_value(&mut self, state: &S) -> f64 {
+        state.get_score_rate()
+    }
+
+    fn expand_threshold(&self) -> usize {
+        1
+    }
+
+    fn selection_value(&self, w: f64, n: usize, _t: usize) -> f64 {
+        1.0 - w / n as f64
+    }
+}
+
+// 探索回数を指定してThunder探索で行動を決定する
+pub fn thunder_search_action<S: AlternateGameState>(state: &S, playout_number: usize) -> usize {
+    let mut root_node = Node::new(state.clone(), NO_ACTION);
+    root_node.expand();
+    for _ in 0..playout_number {
+        root_node.evaluate(&mut Thunder);
+    }
+    root_node.best_action()
+}
+
+// 制限時間(秒)まで探索を繰り返してThunder探索で行動を決定する
+pub fn thunder_search_action_with_time_threshold<S: AlternateGameState>(state: &S, time_threshold: f64) -> usize {
+    let mut root_node = Node::new(state.clone(), NO_ACTION);
+    root_node.expand();
+    let time_keeper = TimeKeeper::new(time_threshold);
+    loop {
+        if time_keeper.isTimeOver() {
+            break;
+        }
+        root_node.evaluate(&mut Thunder);
+    }
+    root_node.best_action()
+}
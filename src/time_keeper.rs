@@ -0,0 +1,32 @@
+// 経過時間を管理して時間制限付きの探索を打ち切るための構造体
+
+#[derive(Debug, Clone)]
+pub struct TimeKeeper {
+    start_time: std::time::Instant,
+    time_threshold: f64,
+}
+
+impl TimeKeeper {
+    pub fn new(time_threshold: f64) -> Self {
+        TimeKeeper {
+            start_time: std::time::Instant::now(),
+            time_threshold,
+        }
+    }
+    #[inline]
+    pub fn isTimeOver(&self) -> bool {
+        let elapsed_time = self.getElapsedTime();
+        #[cfg(feature = "local")]
+        {
+            elapsed_time * 0.85 >= self.time_threshold
+        }
+        #[cfg(not(feature = "local"))]
+        {
+            elapsed_time >= self.time_threshold
+        }
+    }
+
+    pub fn getElapsedTime(&self) -> f64 {
+        self.start_time.elapsed().as_nanos() as f64 * 1e-9
+    }
+}
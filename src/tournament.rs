@@ -0,0 +1,97 @@
+// 2人交互ゲームの任意の数のAIによる総当たり戦
+// 各組は同じ初期状態で先手後手を入れ替えて対戦し、対戦表とElo ratingを表示する
+
+use crate::battle::play_alternate_game;
+use crate::random::game_rng;
+use crate::statistics::{fit_elo_ratings, MatchResult};
+use crate::worker_pool::run_tasks;
+use crate::{AlternateGameState, StringAIPair};
+
+// 並列に対戦させるとき、スレッドごとに参加する全てのAIを作り直すための関数
+pub type AIListFactory<S> = dyn Fn() -> Vec<StringAIPair<S>> + Sync;
+
+// Elo ratingを推定するときに各組に加える仮想的な引き分けの数
+const PRIOR_DRAWS: f64 = 2.0;
+
+#[derive(Clone, Debug)]
+pub struct TournamentResult {
+    pub names: Vec<String>,
+    // results[i][j]はi番目のAIから見たj番目のAIとの成績
+    pub results: Vec<Vec<MatchResult>>,
+    pub ratings: Vec<f64>,
+}
+impl TournamentResult {
+    pub fn to_string(&self) {
+        let n = self.names.len();
+        let width = self.names.iter().map(|name| name.len()).max().unwrap_or(0).max(6);
+        let mut header = format!("{:width$}", "", width = width);
+        for name in self.names.iter() {
+            header += &format!(" {:>width$}", name, width = width);
+        }
+        header += &format!(" {:>7} {:>7}", "score", "Elo");
+        println!("{}", header);
+        for i in 0..n {
+            let mut line = format!("{:width$}", self.names[i], width = width);
+            let mut total = MatchResult::default();
+            for j in 0..n {
+                if i == j {
+                    line += &format!(" {:>width$}", "-", width = width);
+                    continue;
+                }
+                let result = &self.results[i][j];
+                line += &format!(" {:>width$.3}", result.score(), width = width);
+                total.wins += result.wins;
+                total.draws += result.draws;
+                total.losses += result.losses;
+            }
+            line += &format!(" {:>7.3} {:>7.1}", total.score(), self.ratings[i]);
+            println!("{}", line);
+        }
+
+        println!("ranking");
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&a, &b| self.ratings[b].total_cmp(&self.ratings[a]));
+        for (rank, &i) in order.iter().enumerate() {
+            println!("{}. {} {:.1}", rank + 1, self.names[i], self.ratings[i]);
+        }
+    }
+}
+
+// 全ての組についてnew_state(i)から始まるゲームを先手後手を交代しながらgame_number×2回ずつプレイする
+// AIの乱数はseedから組と番号ごとに作るので(Noneならエントロピーから)、
+// thread_number本のスレッドで並列にプレイしても、seedが同じなら同じ結果を組と番号の順に表示して集計する
+pub fn round_robin<S: AlternateGameState>(
+    new_ais: &AIListFactory<S>,
+    game_number: usize,
+    new_state: impl Fn(usize) -> S + Sync,
+    seed: Option<u64>,
+    thread_number: usize,
+) -> TournamentResult {
+    let names: Vec<String> = new_ais().into_iter().map(|ai| ai.0).collect();
+    let n = names.len();
+    let pairs: Vec<(usize, usize)> = (0..n).flat_map(|a| (a + 1..n).map(move |b| (a, b))).collect();
+    let mut results = vec![vec![MatchResult::default(); n]; n];
+    run_tasks(
+        pairs.len() * game_number,
+        thread_number,
+        new_ais,
+        |ais, index| {
+            let (a, b) = pairs[index / game_number];
+            let base_state = new_state(index % game_number);
+            let mut rng = game_rng(seed, index);
+            let first = play_alternate_game(&*ais[a].1, &*ais[b].1, base_state.clone(), &mut rng).0;
+            let second = 1.0 - play_alternate_game(&*ais[b].1, &*ais[a].1, base_state, &mut rng).0;
+            [first, second]
+        },
+        |index, win_rate_points| {
+            let (a, b) = pairs[index / game_number];
+            for &win_rate_point in win_rate_points {
+                results[a][b].add(win_rate_point);
+                results[b][a].add(1.0 - win_rate_point);
+            }
+            eprintln!("{} vs {} game {}: {:?}", names[a], names[b], index % game_number, win_rate_points);
+        },
+    );
+    let ratings = fit_elo_ratings(&results, PRIOR_DRAWS);
+    TournamentResult { names, results, ratings }
+}
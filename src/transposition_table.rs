@@ -0,0 +1,218 @@
+// alpha-beta法で同一局面の探索結果を再利用するための置換表
+
+use std::cell::Cell;
+
+use crate::search::NO_ACTION;
+use crate::{HashedGameState, ScoreType};
+
+// 保存した評価値が真の値、下界、上界のどれか
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    EXACT,
+    LOWER,
+    UPPER,
+}
+
+// scoreはその局面の評価値からの差分で持つ
+// (同じ局面でもそれまでに得た点数の内訳は経路によって異なるため)
+#[derive(Clone, Copy, Debug)]
+pub struct TTEntry {
+    pub hash: u64,
+    pub depth: usize,
+    pub score: ScoreType,
+    pub bound: Bound,
+    pub best_action: usize,
+}
+
+// 2^bits個のエントリを持つ固定サイズの表。同じ位置に入る場合は深く探索した結果を優先する
+#[derive(Clone, Debug)]
+pub struct TranspositionTable {
+    entries: Vec<Option<TTEntry>>,
+    mask: usize,
+    pub hit: usize,
+    pub miss: usize,
+}
+impl TranspositionTable {
+    pub fn new(bits: usize) -> Self {
+        TranspositionTable {
+            entries: vec![None; 1 << bits],
+            mask: (1 << bits) - 1,
+            hit: 0,
+            miss: 0,
+        }
+    }
+
+    pub fn probe(&mut self, hash: u64) -> Option<TTEntry> {
+        match self.entries[hash as usize & self.mask] {
+            Some(entry) if entry.hash == hash => {
+                self.hit += 1;
+                Some(entry)
+            }
+            _ => {
+                self.miss += 1;
+                None
+            }
+        }
+    }
+
+    pub fn store(&mut self, entry: TTEntry) {
+        let slot = &mut self.entries[entry.hash as usize & self.mask];
+        match slot {
+            Some(old) if old.hash == entry.hash && old.depth > entry.depth => {}
+            _ => *slot = Some(entry),
+        }
+    }
+}
+
+thread_local! {
+    static STATISTICS: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+}
+
+// このスレッドで使われた置換表のヒット数とミス数の累計を返してリセットする
+pub fn take_statistics() -> (usize, usize) {
+    STATISTICS.with(|statistics| statistics.replace((0, 0)))
+}
+
+fn record_statistics(table: &TranspositionTable) {
+    STATISTICS.with(|statistics| {
+        let (hit, miss) = statistics.get();
+        statistics.set((hit + table.hit, miss + table.miss));
+    });
+}
+
+// 置換表を使うalpha-betaのスコア計算(fail-soft)
+pub fn alpha_beta_score_with_table<S: HashedGameState>(
+    state: &S,
+    mut alpha: ScoreType,
+    beta: ScoreType,
+    depth: usize,
+    table: &mut TranspositionTable,
+) -> ScoreType {
+    if state.is_done() || depth == 0 {
+        return state.evaluate();
+    }
+    let mut legal_actions = state.legal_actions();
+    if legal_actions.is_empty() {
+        return state.evaluate();
+    }
+    let base_score = state.evaluate();
+    let hash = state.zobrist_hash();
+    let original_alpha = alpha;
+    if let Some(entry) = table.probe(hash) {
+        if entry.depth >= depth {
+            let score = entry.score + base_score;
+            let is_cutoff = match entry.bound {
+                Bound::EXACT => true,
+                Bound::LOWER => score >= beta,
+                Bound::UPPER => score <= alpha,
+            };
+            if is_cutoff {
+                return score;
+            }
+        }
+        // 前回の最善手から調べる
+        if let Some(index) = legal_actions.iter().position(|&action| action == entry.best_action) {
+            legal_actions[..=index].rotate_right(1);
+        }
+    }
+    let mut best_score = -1_000_000;
+    let mut best_action = NO_ACTION;
+    for action in legal_actions {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        let score = -alpha_beta_score_with_table(&next_state, -beta, -alpha, depth - 1, table);
+        if score > best_score {
+            best_score = score;
+            best_action = action;
+        }
+        if score > alpha {
+            alpha = score;
+        }
+        if alpha >= beta {
+            break;
+        }
+    }
+    let bound = if best_score <= original_alpha {
+        Bound::UPPER
+    } else if best_score >= beta {
+        Bound::LOWER
+    } else {
+        Bound::EXACT
+    };
+    table.store(TTEntry {
+        hash,
+        depth,
+        score: best_score - base_score,
+        bound,
+        best_action,
+    });
+    best_score
+}
+
+// 2^table_bitsの大きさの置換表を使い、深さを指定してalpha-betaで行動を決定する
+pub fn alpha_beta_action_with_table<S: HashedGameState>(state: &S, depth: usize, table_bits: usize) -> usize {
+    let mut table = TranspositionTable::new(table_bits);
+    let mut best_action = NO_ACTION;
+    let mut alpha = -1_000_000;
+    let beta = 1_000_000;
+    for action in state.legal_actions() {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        let score = -alpha_beta_score_with_table(&next_state, -beta, -alpha, depth, &mut table);
+        if score > alpha {
+            alpha = score;
+            best_action = action;
+        }
+    }
+    record_statistics(&table);
+    best_action
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{alpha_beta_action, alpha_beta_score, random_action};
+    use crate::{ch5, ch8, new_rng, GameState};
+
+    // 置換表を使っても使わなくても、同じ行動と評価値になることを確かめる
+    // 表が小さく衝突して上書きされる場合も含める
+    fn assert_same_as_alpha_beta<S: HashedGameState>(state: &S, depth: usize, what: &str) {
+        let expected = alpha_beta_score(state, -1_000_000, 1_000_000, depth);
+        for table_bits in [4, 16] {
+            let mut table = TranspositionTable::new(table_bits);
+            let score = alpha_beta_score_with_table(state, -1_000_000, 1_000_000, depth, &mut table);
+            assert_eq!(score, expected, "{} depth {} table_bits {}", what, depth, table_bits);
+            assert_eq!(
+                alpha_beta_action_with_table(state, depth, table_bits),
+                alpha_beta_action(state, depth),
+                "{} depth {} table_bits {}",
+                what,
+                depth,
+                table_bits
+            );
+        }
+    }
+
+    #[test]
+    fn table_matches_alpha_beta_on_maze() {
+        let mut rng = new_rng(Some(0));
+        for seed in 0..10 {
+            let mut state = ch5::MazeState::new(Some(seed));
+            while !state.is_done() {
+                for depth in 1..=5 {
+                    assert_same_as_alpha_beta(&state, depth, &format!("seed {} turn {}", seed, state.turn));
+                }
+                state.advance(random_action(&state, &mut rng));
+            }
+        }
+    }
+
+    #[test]
+    fn table_matches_alpha_beta_on_connect_four() {
+        for (index, state) in ch8::get_sample_states::<ch8::BitboardState>(20).iter().enumerate() {
+            for depth in 1..=4 {
+                assert_same_as_alpha_beta(state, depth, &format!("sample {}", index));
+            }
+        }
+    }
+}
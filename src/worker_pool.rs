@@ -0,0 +1,97 @@
+// 番号付きの独立なタスクを複数のスレッドで分担して実行する
+// 結果は番号順に揃えて返すので、スレッド数によらず同じ順番で集計や表示ができる
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+// 0..task_numberのタスクをthread_number本のスレッドで実行し、結果を番号順に並べて返す
+// 各スレッドは最初にnew_contextでcontextを1つ作り、自分が受け持つタスクで使い回す
+// (Rcを含むAIのようにスレッド間で共有できないものはcontextで各スレッドに作る)
+// on_resultには番号の小さい順に結果が渡される。thread_numberが1以下なら呼び出し元のスレッドで順に実行する
+pub fn run_tasks<C, T, NC, F, R>(
+    task_number: usize,
+    thread_number: usize,
+    new_context: NC,
+    task: F,
+    mut on_result: R,
+) -> Vec<T>
+where
+    NC: Fn() -> C + Sync,
+    F: Fn(&mut C, usize) -> T + Sync,
+    T: Send,
+    R: FnMut(usize, &T),
+{
+    run_tasks_until(task_number, thread_number, new_context, task, |index, result| {
+        on_result(index, result);
+        true
+    })
+}
+
+// run_tasksと同じだが、on_resultがfalseを返したらそれ以降のタスクは実行せず、そこまでの結果を返す
+// 打ち切る位置は番号順に判定するので、スレッド数によらず同じ結果になる
+pub fn run_tasks_until<C, T, NC, F, R>(
+    task_number: usize,
+    thread_number: usize,
+    new_context: NC,
+    task: F,
+    mut on_result: R,
+) -> Vec<T>
+where
+    NC: Fn() -> C + Sync,
+    F: Fn(&mut C, usize) -> T + Sync,
+    T: Send,
+    R: FnMut(usize, &T) -> bool,
+{
+    if thread_number <= 1 {
+        let mut context = new_context();
+        let mut results = Vec::new();
+        for index in 0..task_number {
+            let result = task(&mut context, index);
+            let is_continue = on_result(index, &result);
+            results.push(result);
+            if !is_continue {
+                break;
+            }
+        }
+        return results;
+    }
+
+    let next_index = AtomicUsize::new(0);
+    let is_stopped = AtomicBool::new(false);
+    let mut results: Vec<Option<T>> = (0..task_number).map(|_| None).collect();
+    let mut reported = 0;
+    std::thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..thread_number.min(task_number) {
+            let sender = sender.clone();
+            let (next_index, is_stopped, new_context, task) = (&next_index, &is_stopped, &new_context, &task);
+            scope.spawn(move || {
+                let mut context = new_context();
+                while !is_stopped.load(Ordering::Relaxed) {
+                    let index = next_index.fetch_add(1, Ordering::Relaxed);
+                    if index >= task_number {
+                        break;
+                    }
+                    sender.send((index, task(&mut context, index))).unwrap();
+                }
+            });
+        }
+        drop(sender);
+
+        // 終わった順に受け取り、先頭から番号が揃った分だけon_resultに渡す
+        // 打ち切った後も、実行中のタスクが終わるまで受け取り続ける
+        for (index, result) in receiver {
+            results[index] = Some(result);
+            while !is_stopped.load(Ordering::Relaxed) {
+                let Some(Some(result)) = results.get(reported) else {
+                    break;
+                };
+                if !on_result(reported, result) {
+                    is_stopped.store(true, Ordering::Relaxed);
+                }
+                reported += 1;
+            }
+        }
+    });
+    results.into_iter().take(reported).map(Option::unwrap).collect()
+}
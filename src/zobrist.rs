@@ -0,0 +1,70 @@
+// 盤面をZobrist hashで識別するための乱数表
+// マスの点数、キャラクターの位置、手番、ターン数それぞれに乱数を割り当て、該当するもののXORを局面のハッシュとする
+
+use rand::{Rng, SeedableRng};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZobristTable {
+    h: usize,
+    w: usize,
+    max_point: usize,
+    cell_keys: Vec<u64>,
+    character_keys: Vec<u64>,
+    turn_keys: Vec<u64>,
+    side_key: u64,
+}
+impl ZobristTable {
+    // 同じ大きさなら常に同じ表になるよう固定のseedで生成する
+    pub fn new(h: usize, w: usize, max_point: usize, n_character: usize, end_turn: usize) -> Self {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x5eed_2b15);
+        let mut gen_keys = |n: usize| (0..n).map(|_| rng.gen::<u64>()).collect::<Vec<u64>>();
+        let cell_keys = gen_keys(h * w * (max_point + 1));
+        let character_keys = gen_keys(n_character * h * w);
+        let turn_keys = gen_keys(end_turn + 1);
+        let side_key = gen_keys(1)[0];
+        ZobristTable {
+            h,
+            w,
+            max_point,
+            cell_keys,
+            character_keys,
+            turn_keys,
+            side_key,
+        }
+    }
+
+    // 点数0のマスはハッシュに寄与しない
+    #[inline]
+    pub fn cell(&self, y: usize, x: usize, point: usize) -> u64 {
+        if point == 0 {
+            return 0;
+        }
+        self.cell_keys[(y * self.w + x) * (self.max_point + 1) + point]
+    }
+
+    #[inline]
+    pub fn character(&self, id: usize, y: usize, x: usize) -> u64 {
+        self.character_keys[(id * self.h + y) * self.w + x]
+    }
+
+    #[inline]
+    pub fn turn(&self, turn: usize) -> u64 {
+        self.turn_keys[turn]
+    }
+
+    #[inline]
+    pub fn side(&self) -> u64 {
+        self.side_key
+    }
+
+    // 盤面全体の点数から計算したハッシュ
+    pub fn grid_hash(&self, grid: &[Vec<usize>]) -> u64 {
+        let mut hash = 0;
+        for (y, row) in grid.iter().enumerate() {
+            for (x, &point) in row.iter().enumerate() {
+                hash ^= self.cell(y, x, point);
+            }
+        }
+        hash
+    }
+}